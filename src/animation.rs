@@ -0,0 +1,314 @@
+use std::error::Error;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::Vector;
+
+// Where the camera is and how it's set up at a moment in time.
+#[derive(Copy, Clone)]
+pub struct Keyframe {
+    // In seconds.
+    pub time: f32,
+    pub position: Vector,
+    pub target: Vector,
+    // In degrees.
+    pub fov: f32,
+    pub focus_distance: f32,
+}
+
+impl Keyframe {
+    fn values(&self) -> [f32; 8] {
+        [
+            self.position[0],
+            self.position[1],
+            self.position[2],
+            self.target[0],
+            self.target[1],
+            self.target[2],
+            self.fov,
+            self.focus_distance,
+        ]
+    }
+
+    fn from_values(time: f32, values: [f32; 8]) -> Self {
+        Self {
+            time,
+            position: Vector::new(values[0], values[1], values[2]),
+            target: Vector::new(values[3], values[4], values[5]),
+            fov: values[6],
+            focus_distance: values[7],
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Interpolation {
+    Linear,
+    // A smooth curve through every keyframe, heading from the one before towards the one after.
+    CatmullRom,
+    // Keyframes are alternately points on the curve and two control points between them, as in
+    // a drawing program. The times of control points are ignored.
+    Bezier,
+}
+
+impl FromStr for Interpolation {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "linear" => Self::Linear,
+            "catmull-rom" => Self::CatmullRom,
+            "bezier" => Self::Bezier,
+            _ => Err("Unknown interpolation.")?,
+        })
+    }
+}
+
+pub struct Animation {
+    keyframes: Vec<Keyframe>,
+    interpolation: Interpolation,
+}
+
+impl Animation {
+    // Reads a line for each keyframe, giving its time, position, target, field of view and,
+    // optionally, focus distance, as in "1.5 13,2,3 0,0,0 20 10". Without a focus distance, the
+    // camera is focused on its target.
+    pub fn load(path: &Path, interpolation: Interpolation) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&fs::read_to_string(path)?, interpolation)
+    }
+
+    fn parse(text: &str, interpolation: Interpolation) -> Result<Self, Box<dyn Error>> {
+        let mut keyframes = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let fields: Vec<_> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != 4 && fields.len() != 5 {
+                Err("Keyframes need a time, position, target, field of view and focus distance.")?;
+            }
+            let position: Vector = fields[1].parse()?;
+            let target: Vector = fields[2].parse()?;
+            keyframes.push(Keyframe {
+                time: fields[0].parse()?,
+                position,
+                target,
+                fov: fields[3].parse()?,
+                focus_distance: match fields.get(4) {
+                    Some(field) => field.parse()?,
+                    None => (position - target).length(),
+                },
+            });
+        }
+
+        if keyframes.is_empty() {
+            Err("No keyframes.")?;
+        }
+        if let Interpolation::Bezier = interpolation {
+            if keyframes.len() % 3 != 1 {
+                Err("Bezier curves need two control points between each pair of keyframes.")?;
+            }
+        }
+        let anchors = match interpolation {
+            Interpolation::Bezier => 3,
+            _ => 1,
+        };
+        if keyframes
+            .iter()
+            .step_by(anchors)
+            .zip(keyframes.iter().step_by(anchors).skip(1))
+            .any(|(a, b)| a.time >= b.time)
+        {
+            Err("Keyframes must be in order of time.")?;
+        }
+        Ok(Self {
+            keyframes,
+            interpolation,
+        })
+    }
+
+    // The camera at `time`, which holds still before the first keyframe and after the last.
+    pub fn at(&self, time: f32) -> Keyframe {
+        let step = match self.interpolation {
+            Interpolation::Bezier => 3,
+            _ => 1,
+        };
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+        if keys.len() == 1 || time <= keys[0].time {
+            return Keyframe { time, ..keys[0] };
+        }
+        if time >= keys[last].time {
+            return Keyframe { time, ..keys[last] };
+        }
+
+        // The segment starting at keyframe `i` contains `time`.
+        let i = (0..last)
+            .step_by(step)
+            .take_while(|&i| keys[i].time <= time)
+            .last()
+            .unwrap_or(0);
+        let (start, end) = (&keys[i], &keys[i + step]);
+        let span = end.time - start.time;
+        let u = (time - start.time) / span;
+
+        let mut values = [0.0; 8];
+        match self.interpolation {
+            Interpolation::Linear => {
+                let (a, b) = (start.values(), end.values());
+                for (k, value) in values.iter_mut().enumerate() {
+                    *value = a[k] + (b[k] - a[k]) * u;
+                }
+            }
+            Interpolation::CatmullRom => {
+                // Cubic Hermite, with tangents from the neighbouring keyframes.
+                let tangent = |j: usize| {
+                    let (before, after) = (&keys[j.saturating_sub(1)], &keys[(j + 1).min(last)]);
+                    let (a, b) = (before.values(), after.values());
+                    let mut tangent = [0.0; 8];
+                    for k in 0..8 {
+                        tangent[k] = (b[k] - a[k]) / (after.time - before.time);
+                    }
+                    tangent
+                };
+                let (a, b) = (start.values(), end.values());
+                let (m0, m1) = (tangent(i), tangent(i + 1));
+                let (u2, u3) = (u * u, u * u * u);
+                for (k, value) in values.iter_mut().enumerate() {
+                    *value = (2.0 * u3 - 3.0 * u2 + 1.0) * a[k]
+                        + (u3 - 2.0 * u2 + u) * span * m0[k]
+                        + (-2.0 * u3 + 3.0 * u2) * b[k]
+                        + (u3 - u2) * span * m1[k];
+                }
+            }
+            Interpolation::Bezier => {
+                let points = [
+                    keys[i].values(),
+                    keys[i + 1].values(),
+                    keys[i + 2].values(),
+                    keys[i + 3].values(),
+                ];
+                let v = 1.0 - u;
+                let weights = [v * v * v, 3.0 * v * v * u, 3.0 * v * u * u, u * u * u];
+                for (k, value) in values.iter_mut().enumerate() {
+                    *value = (0..4).map(|p| weights[p] * points[p][k]).sum();
+                }
+            }
+        }
+        Keyframe::from_values(time, values)
+    }
+}
+
+// Frame numbers to render, as "first..end", with the end left out.
+pub struct Frames(pub Range<usize>);
+
+impl FromStr for Frames {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const MSG: &str = "Frames should be given as \"first..end\".";
+        let mut parts = s.splitn(2, "..");
+        let start = parts.next().and_then(|s| s.parse().ok()).ok_or(MSG)?;
+        let end = parts.next().and_then(|s| s.parse().ok()).ok_or(MSG)?;
+        Ok(Self(start..end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &Keyframe, b: [f32; 8]) -> bool {
+        a.values().iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-4)
+    }
+
+    #[test]
+    fn keyframes_are_parsed() {
+        let animation = Animation::parse(
+            "# Time, position, target, fov and focus.\n\
+             0 0,0,3 0,0,0 20\n\
+             \n\
+             2 1,2,3 4,5,6 30 7 # Refocused.\n",
+            Interpolation::Linear,
+        )
+        .unwrap();
+        assert!(close(
+            &animation.keyframes[0],
+            [0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 20.0, 3.0]
+        ));
+        assert!(close(
+            &animation.keyframes[1],
+            [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 30.0, 7.0]
+        ));
+    }
+
+    #[test]
+    fn malformed_keyframes_are_rejected() {
+        let linear = [
+            "",
+            "0 0,0,0 1,0,0\n",
+            "0 0,0,0 1,0,0 20 1 2\n",
+            "0 0,0 1,0,0 20\n",
+            "1 0,0,0 1,0,0 20\n0 0,0,0 1,0,0 20\n",
+            "1 0,0,0 1,0,0 20\n1 0,0,0 1,0,0 20\n",
+        ];
+        for text in &linear {
+            assert!(
+                Animation::parse(text, Interpolation::Linear).is_err(),
+                "{:?}",
+                text
+            );
+        }
+        let bezier = "0 0,0,0 1,0,0 20\n1 0,0,0 1,0,0 20\n";
+        assert!(Animation::parse(bezier, Interpolation::Bezier).is_err());
+    }
+
+    // Keyframes evenly spaced along a line, in time and space.
+    const LINE: &str = "0 0,0,0 0,0,-1 20\n1 1,0,0 1,0,-1 30\n2 2,0,0 2,0,-1 40\n";
+
+    #[test]
+    fn interpolation_passes_through_keyframes_and_holds_outside_them() {
+        for interpolation in &[Interpolation::Linear, Interpolation::CatmullRom] {
+            let animation = Animation::parse(LINE, *interpolation).unwrap();
+            for key in &animation.keyframes {
+                assert!(close(&animation.at(key.time), key.values()));
+            }
+            assert!(close(&animation.at(-1.0), animation.keyframes[0].values()));
+            assert!(close(&animation.at(5.0), animation.keyframes[2].values()));
+            assert_eq!(animation.at(5.0).time, 5.0);
+        }
+    }
+
+    #[test]
+    fn interpolation_follows_its_curve() {
+        let halfway = [0.5, 0.0, 0.0, 0.5, 0.0, -1.0, 25.0, 1.0];
+        let linear = Animation::parse(LINE, Interpolation::Linear).unwrap();
+        assert!(close(&linear.at(0.5), halfway));
+        // Evenly spaced keyframes keep the spline straight, even at the last one, whose tangent only
+        // looks back.
+        let spline = Animation::parse(LINE, Interpolation::CatmullRom).unwrap();
+        assert!(close(
+            &spline.at(1.5),
+            [1.5, 0.0, 0.0, 1.5, 0.0, -1.0, 35.0, 1.0]
+        ));
+
+        let bezier = Animation::parse(
+            "0 0,0,0 0,0,-1 20\n0 0,3,0 0,0,-1 20\n0 3,3,0 0,0,-1 20\n1 3,0,0 0,0,-1 20\n",
+            Interpolation::Bezier,
+        )
+        .unwrap();
+        let middle = bezier.at(0.5);
+        assert!((middle.position - Vector::new(1.5, 2.25, 0.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn frames_are_parsed() {
+        assert_eq!("3..10".parse::<Frames>().unwrap().0, 3..10);
+        for text in &["3", "..10", "a..b", "3..", ""] {
+            assert!(text.parse::<Frames>().is_err(), "{:?}", text);
+        }
+    }
+}
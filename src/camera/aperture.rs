@@ -0,0 +1,147 @@
+use std::error::Error;
+use std::f32::consts::PI;
+use std::path::Path;
+
+use crate::ppm;
+use crate::sampler::Sampler;
+
+// The shape of the opening in a lens, which defocused highlights take on. Sizes are relative to
+// the lens radius.
+#[derive(Clone)]
+pub enum Aperture {
+    Disk,
+    // A regular polygon inscribed in the lens, as formed by straight diaphragm blades.
+    Polygon { blades: usize, rotation: f32 },
+    // An image covering the square around the lens, letting through light in proportion to its
+    // brightness.
+    Mask(Mask),
+}
+
+#[derive(Clone)]
+pub struct Mask {
+    dimensions: (usize, usize),
+    values: Vec<f32>,
+    mean: f32,
+    // Cumulative distributions over the rows, and over the pixels within each row.
+    rows: Vec<f32>,
+    columns: Vec<f32>,
+}
+
+impl Aperture {
+    pub fn mask(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let (width, height, pixels) = ppm::read(path)?;
+        let values: Vec<_> = pixels
+            .iter()
+            .map(|pixel| (pixel[0] + pixel[1] + pixel[2]) / 3.0)
+            .collect();
+        let mut rows = Vec::with_capacity(height + 1);
+        let mut columns = Vec::with_capacity((width + 1) * height);
+        rows.push(0.0);
+        for row in values.chunks(width) {
+            let start = columns.len();
+            columns.push(0.0);
+            for (i, value) in row.iter().enumerate() {
+                columns.push(columns[start + i] + value);
+            }
+            let total = columns[start + width];
+            if total > 0.0 {
+                columns[start..].iter_mut().for_each(|sum| *sum /= total);
+            }
+            rows.push(rows[rows.len() - 1] + total);
+        }
+        let total = rows[height];
+        if total <= 0.0 {
+            Err("Aperture mask is black.")?;
+        }
+        rows.iter_mut().for_each(|sum| *sum /= total);
+
+        Ok(Self::Mask(Mask {
+            dimensions: (width, height),
+            mean: total / values.len() as f32,
+            values,
+            rows,
+            columns,
+        }))
+    }
+
+    // Area of the aperture, weighted by how much light gets through.
+    pub fn area(&self) -> f32 {
+        match self {
+            Self::Disk => PI,
+            Self::Polygon { blades, .. } => {
+                *blades as f32 / 2.0 * (2.0 * PI / *blades as f32).sin()
+            }
+            Self::Mask(mask) => 4.0 * mask.mean,
+        }
+    }
+
+    // Radius of the smallest circle around the aperture.
+    pub fn extent(&self) -> f32 {
+        match self {
+            Self::Mask(_) => 2.0f32.sqrt(),
+            _ => 1.0,
+        }
+    }
+
+    // A point on the aperture, chosen in proportion to the light let through there, along with
+    // its density relative to a uniform choice over `area`.
+    pub fn sample(&self, sampler: &mut dyn Sampler) -> ((f32, f32), f32) {
+        match self {
+            Self::Disk => {
+                let point = sampler.in_unit_disk();
+                ((point[0], point[1]), 1.0)
+            }
+            Self::Polygon { blades, rotation } => {
+                let (u, v) = sampler.next_2d();
+                // Pick a triangle between the centre and one side, then a point within it.
+                let side = u * *blades as f32;
+                let (index, u) = (side.floor(), side.fract());
+                let corner = |index: f32| {
+                    let angle = rotation + 2.0 * PI * index / *blades as f32;
+                    (angle.cos(), angle.sin())
+                };
+                let (a, b) = (corner(index), corner(index + 1.0));
+                let scale = u.sqrt();
+                (
+                    (
+                        scale * ((1.0 - v) * a.0 + v * b.0),
+                        scale * ((1.0 - v) * a.1 + v * b.1),
+                    ),
+                    1.0,
+                )
+            }
+            Self::Mask(mask) => mask.sample(sampler.next_2d()),
+        }
+    }
+}
+
+impl Mask {
+    fn sample(&self, u: (f32, f32)) -> ((f32, f32), f32) {
+        let (width, height) = self.dimensions;
+        let (row, y) = invert(&self.rows, u.1);
+        let (column, x) = invert(
+            &self.columns[row * (width + 1)..(row + 1) * (width + 1)],
+            u.0,
+        );
+
+        // Rows go from the top down.
+        let point = (
+            2.0 * (column as f32 + x) / width as f32 - 1.0,
+            1.0 - 2.0 * (row as f32 + y) / height as f32,
+        );
+        (point, self.values[row * width + column] / self.mean)
+    }
+}
+
+// The bin of a cumulative distribution `u` falls in, and how far along the bin it is.
+fn invert(cdf: &[f32], u: f32) -> (usize, f32) {
+    let bins = cdf.len() - 1;
+    let bin = cdf.partition_point(|&value| value <= u).clamp(1, bins) - 1;
+    let width = cdf[bin + 1] - cdf[bin];
+    let within = if width > 0.0 {
+        ((u - cdf[bin]) / width).min(1.0 - f32::EPSILON)
+    } else {
+        0.5
+    };
+    (bin, within)
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::Ray;
+
+// Six square faces side by side, looking along +x, -x, +y, -y, +z and -z of the camera, where x is
+// right, y up and -z the view direction. Each face is as seen from the inside, with the top and
+// bottom faces joining the view direction's face. Images should be six times as wide as they are
+// tall.
+pub struct CubeMap {
+    view: View,
+}
+
+impl CubeMap {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(view: View) -> Arc<dyn Camera> {
+        Arc::new(Self { view })
+    }
+}
+
+impl Camera for CubeMap {
+    fn ray_from(&self, pos: (f32, f32), _sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let across = (pos.0 * 6.0).clamp(0.0, 5.999_999);
+        let face = across as usize;
+        let a = 2.0 * across.fract() - 1.0;
+        let b = 2.0 * pos.1 - 1.0;
+        let (x, y, z) = match face {
+            0 => (1.0, b, a),
+            1 => (-1.0, b, -a),
+            2 => (a, 1.0, b),
+            3 => (a, -1.0, -b),
+            4 => (-a, b, 1.0),
+            _ => (a, b, -1.0),
+        };
+        Some(Ray::new(self.view.origin, self.view.direction(x, y, z)).into())
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+}
@@ -0,0 +1,37 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::Ray;
+
+// Every direction around the camera, with longitude across the image and latitude up it, as used
+// for 360 degree stills and environment maps. The view direction is at the centre, and images
+// should be twice as wide as they are tall.
+pub struct Equirectangular {
+    view: View,
+}
+
+impl Equirectangular {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(view: View) -> Arc<dyn Camera> {
+        Arc::new(Self { view })
+    }
+}
+
+impl Camera for Equirectangular {
+    fn ray_from(&self, pos: (f32, f32), _sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let longitude = 2.0 * PI * (pos.0 - 0.5);
+        let latitude = PI * (pos.1 - 0.5);
+        let direction = self.view.direction(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        );
+        Some(Ray::new(self.view.origin, direction).into())
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+}
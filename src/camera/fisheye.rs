@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::Ray;
+
+// How the angle from the view direction maps to distance from the centre of the image.
+#[derive(Copy, Clone)]
+pub enum Mapping {
+    // Proportional to the angle, so angles can be measured off the image.
+    Equidistant,
+    // Proportional to the sine of half the angle, so areas keep their solid angle.
+    Equisolid,
+}
+
+impl Mapping {
+    // Distance from the centre of the image at `angle` from the axis, for a unit focal length.
+    pub fn radius(&self, angle: f32) -> f32 {
+        match self {
+            Self::Equidistant => angle,
+            Self::Equisolid => 2.0 * (angle / 2.0).sin(),
+        }
+    }
+
+    pub fn angle(&self, radius: f32) -> f32 {
+        match self {
+            Self::Equidistant => radius,
+            Self::Equisolid => 2.0 * (radius / 2.0).min(1.0).asin(),
+        }
+    }
+}
+
+// A circular fisheye image, fitted to the height of the film, with `fov` across the circle.
+// Outside the circle, the image is black.
+pub struct Fisheye {
+    view: View,
+    fov: f32,
+    aspect_ratio: f32,
+    mapping: Mapping,
+}
+
+impl Fisheye {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(view: View, fov: f32, aspect_ratio: f32, mapping: Mapping) -> Arc<dyn Camera> {
+        Arc::new(Self {
+            view,
+            fov,
+            aspect_ratio,
+            mapping,
+        })
+    }
+}
+
+impl Camera for Fisheye {
+    fn ray_from(&self, pos: (f32, f32), _sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let x = (2.0 * pos.0 - 1.0) * self.aspect_ratio;
+        let y = 2.0 * pos.1 - 1.0;
+        let radius = (x * x + y * y).sqrt();
+        if radius > 1.0 {
+            return None;
+        }
+
+        let theta = self
+            .mapping
+            .angle(radius * self.mapping.radius(self.fov / 2.0));
+        let (x, y) = if radius > 0.0 {
+            (x / radius, y / radius)
+        } else {
+            (0.0, 0.0)
+        };
+        let direction = self
+            .view
+            .direction(theta.sin() * x, theta.sin() * y, -theta.cos());
+        Some(Ray::new(self.view.origin, direction).into())
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+}
@@ -0,0 +1,115 @@
+mod aperture;
+mod cube_map;
+mod equirectangular;
+mod fisheye;
+mod orthographic;
+mod perspective;
+mod physical;
+mod realistic;
+mod stereo;
+
+pub use aperture::Aperture;
+pub use cube_map::CubeMap;
+pub use equirectangular::Equirectangular;
+pub use fisheye::{Fisheye, Mapping};
+pub use orthographic::Orthographic;
+pub use perspective::{Lens, Perspective};
+pub use physical::Physical;
+pub use realistic::{LensSystem, Realistic};
+pub use stereo::{Layout, Stereo};
+
+use crate::sampler::Sampler;
+use crate::{Ray, Vector};
+
+// Where a camera is and which way it faces, shared by every projection.
+#[derive(Copy, Clone)]
+pub struct View {
+    origin: Vector,
+    // Right, up and backwards.
+    basis: (Vector, Vector, Vector),
+    focus_distance: f32,
+}
+
+impl View {
+    pub fn new(look_from: Vector, look_at: Vector, up: Vector, focus_distance: f32) -> Self {
+        let w = (look_from - look_at).unit();
+        let u = up.cross(&w).unit();
+        let v = w.cross(&u);
+        Self {
+            origin: look_from,
+            basis: (u, v, w),
+            focus_distance,
+        }
+    }
+
+    pub fn focus_distance(&self) -> f32 {
+        self.focus_distance
+    }
+
+    // A direction given along the right, up and backwards axes, in world space.
+    fn direction(&self, x: f32, y: f32, z: f32) -> Vector {
+        self.basis.0 * x + self.basis.1 * y + self.basis.2 * z
+    }
+}
+
+// A ray leaving the camera, and how much of each channel of the light coming back along it the film
+// records.
+pub struct CameraRay {
+    pub ray: Ray,
+    pub weight: Vector,
+}
+
+impl From<Ray> for CameraRay {
+    fn from(ray: Ray) -> Self {
+        Self {
+            ray,
+            weight: Vector::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+// A point on the lens from which a given point in the scene is visible.
+pub struct LensSample {
+    pub point: Vector,
+    pub uv: (f32, f32),
+    pub importance: f32,
+    // Solid angle density, as seen from the scene point.
+    pub pdf: f32,
+}
+
+pub trait Camera: Send + Sync {
+    // The ray through `pos` on the film, from the bottom left corner, unless the projection
+    // leaves that part of the image empty.
+    fn ray_from(&self, pos: (f32, f32), sampler: &mut dyn Sampler) -> Option<CameraRay>;
+
+    fn view(&self) -> &View;
+
+    fn forward(&self) -> Vector {
+        -self.view().basis.2
+    }
+
+    fn focus_distance(&self) -> f32 {
+        self.view().focus_distance
+    }
+
+    // What the depth AOV records for a `ray` from the camera hitting `point`.
+    fn depth(&self, ray: &Ray, point: &Vector) -> f32 {
+        (*point - *ray.origin()).length()
+    }
+
+    // Whether light paths can be joined to the camera through `sample_lens`, which projections
+    // with no single centre or a nonlinear mapping leave out.
+    fn connectible(&self) -> bool {
+        false
+    }
+
+    // Solid angle density with which `ray_from` generates a direction, for a uniformly chosen
+    // film position.
+    fn pdf_direction(&self, _ray: &Ray) -> f32 {
+        0.0
+    }
+
+    fn sample_lens(&self, _point: &Vector, _sampler: &mut dyn Sampler) -> Option<LensSample> {
+        None
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::Ray;
+
+// Parallel rays, for drawings where sizes shouldn't shrink with distance. The image covers as much
+// of the focus plane as a perspective camera with the same field of view would.
+pub struct Orthographic {
+    view: View,
+    size: (f32, f32),
+}
+
+impl Orthographic {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(view: View, fov: f32, aspect_ratio: f32) -> Arc<dyn Camera> {
+        let height = 2.0 * view.focus_distance * (fov / 2.0).tan();
+        Arc::new(Self {
+            view,
+            size: (aspect_ratio * height, height),
+        })
+    }
+}
+
+impl Camera for Orthographic {
+    fn ray_from(&self, pos: (f32, f32), _sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let offset = self.view.direction(
+            (pos.0 - 0.5) * self.size.0,
+            (pos.1 - 0.5) * self.size.1,
+            0.0,
+        );
+        Some(Ray::new(self.view.origin + offset, self.forward()).into())
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+}
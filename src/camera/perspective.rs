@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use super::{Aperture, Camera, CameraRay, LensSample, View};
+use crate::sampler::Sampler;
+use crate::{Ray, Vector};
+
+// How a thin lens differs from a perfect one, besides its size.
+#[derive(Clone)]
+pub struct Lens {
+    pub aperture: Aperture,
+    // How much the lens barrel clips the aperture as seen from off-axis, which darkens the edges of
+    // the image and gives defocused highlights there the shape of a cat's eye.
+    pub cat_eye: f32,
+    // Offset of the image, as a fraction of its size, with the film kept parallel to the view.
+    pub shift: (f32, f32),
+    // Rotation of the plane of focus about the horizontal and vertical axes, in radians. Positive
+    // angles bring the top and the right of the image nearer.
+    pub tilt: (f32, f32),
+    // Relative difference in focal length between the red, green and blue channels.
+    pub chromatic_aberration: f32,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Self {
+            aperture: Aperture::Disk,
+            cat_eye: 0.0,
+            shift: (0.0, 0.0),
+            tilt: (0.0, 0.0),
+            chromatic_aberration: 0.0,
+        }
+    }
+}
+
+// A thin lens in front of a flat film, focused at the view's focus distance.
+pub struct Perspective {
+    view: View,
+    lower_left: Vector,
+    horizontal: Vector,
+    vertical: Vector,
+    aspect_ratio: f32,
+    lens_radius: f32,
+    lens: Lens,
+    // Normal of the plane of focus, when tilted away from the film.
+    focus_plane: Option<Vector>,
+}
+
+impl Perspective {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        view: View,
+        fov: f32,
+        aspect_ratio: f32,
+        aperture: f32,
+        lens: Lens,
+    ) -> Arc<dyn Camera> {
+        let viewport_height = 2.0 * (fov / 2.0).tan();
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let (u, v, w) = view.basis;
+        let focus_dist = view.focus_distance;
+        let horizontal = focus_dist * viewport_width * u;
+        let vertical = focus_dist * viewport_height * v;
+        let lower_left = view.origin - horizontal / 2.0 - vertical / 2.0 - focus_dist * w
+            + lens.shift.0 * horizontal
+            + lens.shift.1 * vertical;
+
+        let (tilt_x, tilt_y) = lens.tilt;
+        let focus_plane = if tilt_x != 0.0 || tilt_y != 0.0 {
+            Some((-w * tilt_x.cos() + v * tilt_x.sin()) * tilt_y.cos() + u * tilt_y.sin())
+        } else {
+            None
+        };
+
+        Arc::new(Self {
+            view,
+            horizontal,
+            vertical,
+            lower_left,
+            aspect_ratio,
+            lens_radius: aperture / 2.0,
+            lens,
+            focus_plane,
+        })
+    }
+
+    // Where a ray leaves the lens relative to its centre, for a point on the aperture.
+    fn lens_offset(&self, point: (f32, f32)) -> Vector {
+        self.view.basis.0 * (self.lens_radius * point.0)
+            + self.view.basis.1 * (self.lens_radius * point.1)
+    }
+
+    fn lens_area(&self) -> f32 {
+        if self.lens_radius > 0.0 {
+            self.lens.aperture.area() * self.lens_radius * self.lens_radius
+        } else {
+            1.0
+        }
+    }
+
+    // Whether the lens barrel blocks light through `point` on the aperture from reaching `pos` on
+    // the film. The barrel is seen as a circle as large as the aperture, moving off centre towards
+    // the edges of the image.
+    fn vignetted(&self, point: (f32, f32), pos: (f32, f32)) -> bool {
+        if self.lens.cat_eye == 0.0 {
+            return false;
+        }
+        let centre = (
+            self.lens.cat_eye * (2.0 * pos.0 - 1.0) * self.aspect_ratio,
+            self.lens.cat_eye * (2.0 * pos.1 - 1.0),
+        );
+        let (x, y) = (point.0 - centre.0, point.1 - centre.1);
+        x * x + y * y > self.lens.aperture.extent().powi(2)
+    }
+
+    // Where rays through `film_point`, on the plane at the focus distance, come to a focus.
+    fn focus_point(&self, film_point: Vector) -> Vector {
+        let normal = match self.focus_plane {
+            Some(normal) => normal,
+            None => return film_point,
+        };
+        // Follow the ray through the centre of the lens, which isn't bent.
+        let chief = film_point - self.view.origin;
+        let along = chief.dot(&normal);
+        if along <= 0.0 {
+            // Beyond where the plane of focus meets the horizon.
+            return film_point;
+        }
+        let distance = self.view.focus_distance * self.forward().dot(&normal);
+        self.view.origin + chief * (distance / along)
+    }
+
+    // Area of the image, projected onto a plane at unit distance.
+    fn film_area(&self) -> f32 {
+        let focus_dist = self.view.focus_distance;
+        self.horizontal.length() * self.vertical.length() / (focus_dist * focus_dist)
+    }
+
+    // Where on the film a ray leaving the lens at `lens_point` would be recorded.
+    fn project(&self, lens_point: &Vector, direction: &Vector) -> Option<(f32, f32)> {
+        let direction = direction.unit();
+        let cosine = direction.dot(&self.forward());
+        if cosine <= 0.0 {
+            return None;
+        }
+
+        let film_point = match self.focus_plane {
+            None => *lens_point + direction * (self.view.focus_distance / cosine),
+            Some(normal) => {
+                let along = direction.dot(&normal);
+                if along <= 0.0 {
+                    return None;
+                }
+                let centre = self.view.origin + self.forward() * self.view.focus_distance;
+                let focus_point =
+                    *lens_point + direction * ((centre - *lens_point).dot(&normal) / along);
+                let chief = focus_point - self.view.origin;
+                let depth = chief.dot(&self.forward());
+                if depth <= 0.0 {
+                    return None;
+                }
+                self.view.origin + chief * (self.view.focus_distance / depth)
+            }
+        };
+        let relative = film_point - self.lower_left;
+        let uv = (
+            relative.dot(&self.horizontal) / self.horizontal.length_squared(),
+            relative.dot(&self.vertical) / self.vertical.length_squared(),
+        );
+        if (0.0..1.0).contains(&uv.0) && (0.0..1.0).contains(&uv.1) {
+            Some(uv)
+        } else {
+            None
+        }
+    }
+}
+
+impl Camera for Perspective {
+    fn ray_from(&self, pos: (f32, f32), sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let (point, _) = self.lens.aperture.sample(sampler);
+        if self.vignetted(point, pos) {
+            return None;
+        }
+        let offset = self.lens_offset(point);
+        let origin = self.view.origin;
+
+        let mut weight = Vector::new(1.0, 1.0, 1.0);
+        let mut focus_point =
+            self.focus_point(self.lower_left + pos.0 * self.horizontal + pos.1 * self.vertical);
+        if self.lens.chromatic_aberration != 0.0 {
+            // Each channel is focused as if by a slightly different focal length, which changes
+            // both the size of the image and the distance at which it's sharp.
+            let channel = ((3.0 * sampler.next_1d()) as usize).min(2);
+            let scale = 1.0 + self.lens.chromatic_aberration * (channel as f32 - 1.0);
+            let pos = (0.5 + (pos.0 - 0.5) * scale, 0.5 + (pos.1 - 0.5) * scale);
+            let film_point = self.lower_left + pos.0 * self.horizontal + pos.1 * self.vertical;
+            focus_point = origin + (self.focus_point(film_point) - origin) * scale;
+
+            let mut channels = [0.0; 3];
+            channels[channel] = 3.0;
+            weight = Vector::new(channels[0], channels[1], channels[2]);
+        }
+
+        Some(CameraRay {
+            ray: Ray::new(origin + offset, focus_point - origin - offset),
+            weight,
+        })
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+
+    // Distance along the view direction, so that the film plane has a constant depth.
+    fn depth(&self, ray: &Ray, point: &Vector) -> f32 {
+        (*point - *ray.origin()).dot(&self.forward())
+    }
+
+    // Light tracing would have to pick a channel for each path, so chromatic aberration leaves it
+    // out. So does tilt, which makes the film no longer a projection through the lens centre, as
+    // `pdf_direction` and `sample_lens` assume.
+    fn connectible(&self) -> bool {
+        self.lens.chromatic_aberration == 0.0 && self.focus_plane.is_none()
+    }
+
+    fn pdf_direction(&self, ray: &Ray) -> f32 {
+        if self.project(ray.origin(), ray.direction()).is_none() {
+            return 0.0;
+        }
+        let cosine = ray.direction().unit().dot(&self.forward());
+        1.0 / (self.film_area() * cosine.powi(3))
+    }
+
+    fn sample_lens(&self, point: &Vector, sampler: &mut dyn Sampler) -> Option<LensSample> {
+        let (aperture_point, density) = self.lens.aperture.sample(sampler);
+        let lens_point = self.view.origin + self.lens_offset(aperture_point);
+
+        let direction = *point - lens_point;
+        let uv = self.project(&lens_point, &direction)?;
+        if self.vignetted(aperture_point, uv) {
+            return None;
+        }
+        let cosine = direction.unit().dot(&self.forward());
+        let density = if self.lens_radius > 0.0 { density } else { 1.0 };
+
+        Some(LensSample {
+            point: lens_point,
+            uv,
+            importance: density / (self.film_area() * self.lens_area() * cosine.powi(4)),
+            pdf: direction.length_squared() * density / (cosine * self.lens_area()),
+        })
+    }
+}
@@ -0,0 +1,47 @@
+use super::Mapping;
+
+// The "sunny 16" rule: at f/16, with the shutter open for one over the ISO speed in seconds, a
+// sunlit scene is exposed properly. Scenes are lit as if by daylight, so these settings leave
+// their radiance as it is.
+const REFERENCE_F_NUMBER: f32 = 16.0;
+const REFERENCE_SHUTTER_TIME: f32 = 0.01;
+const REFERENCE_ISO: f32 = 100.0;
+
+// A camera set up the way a photographer would. Lengths on the camera are in millimetres, and
+// scene units are taken to be metres.
+pub struct Physical {
+    pub focal_length: f32,
+    pub sensor_width: f32,
+    pub f_number: f32,
+    // In seconds.
+    pub shutter_time: f32,
+    pub iso: f32,
+}
+
+impl Physical {
+    // Vertical field of view of an image with the sensor's width, for a lens focused at infinity.
+    // Fisheye lenses cover the height of the sensor with their image circle.
+    pub fn field_of_view(&self, aspect_ratio: f32, mapping: Option<Mapping>) -> f32 {
+        let half_height = self.sensor_width / aspect_ratio / 2.0;
+        let angle = match mapping {
+            Some(mapping) => mapping.angle(half_height / self.focal_length),
+            None => (half_height / self.focal_length).atan(),
+        };
+        2.0 * angle
+    }
+
+    // Diameter of the entrance pupil, in scene units.
+    pub fn aperture(&self) -> f32 {
+        self.focal_length / self.f_number / 1000.0
+    }
+
+    // How much brighter the image is than with the reference settings. Light gathered grows with
+    // the area of the aperture and the time the shutter is open, and the sensor's response with
+    // its ISO speed.
+    pub fn exposure(&self) -> f32 {
+        let settings = self.shutter_time * self.iso / (self.f_number * self.f_number);
+        let reference =
+            REFERENCE_SHUTTER_TIME * REFERENCE_ISO / (REFERENCE_F_NUMBER * REFERENCE_F_NUMBER);
+        settings / reference
+    }
+}
@@ -0,0 +1,402 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::{Ray, Vector};
+
+// Rings of the film for which the exit pupil is bounded separately.
+const PUPIL_BINS: usize = 64;
+// Points along each side of the grid searched for the exit pupil.
+const PUPIL_GRID: usize = 128;
+// Radii of curvature beyond which a surface is taken to be flat, in millimetres. Intersecting
+// spheres this large would lose the surface's sag to rounding.
+const FLAT_RADIUS: f32 = 1e5;
+
+// One surface of a lens prescription, in millimetres.
+#[derive(Clone)]
+struct Element {
+    // Positive when the centre of curvature is towards the film, and zero for flat surfaces such
+    // as the aperture stop.
+    radius: f32,
+    // Distance to the next surface, or to the film after the last one.
+    thickness: f32,
+    // Of the medium between this surface and the next.
+    ior: f32,
+    aperture_radius: f32,
+}
+
+// The surfaces of a lens, from the front to the film.
+#[derive(Clone)]
+pub struct LensSystem {
+    elements: Vec<Element>,
+}
+
+impl LensSystem {
+    // Reads a prescription with a line for each surface, giving its radius of curvature,
+    // thickness, index of refraction and aperture diameter, in millimetres, as in lens patents.
+    // Flat surfaces, like the aperture stop, have a radius of zero or "inf", and an index of zero
+    // stands for air.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut elements = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let values = line
+                .split_whitespace()
+                .map(|value| value.parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()?;
+            match values[..] {
+                [] => {}
+                [radius, thickness, ior, aperture] => elements.push(Element {
+                    radius: if radius.abs() > FLAT_RADIUS {
+                        0.0
+                    } else {
+                        radius
+                    },
+                    thickness,
+                    ior: if ior == 0.0 { 1.0 } else { ior },
+                    aperture_radius: aperture / 2.0,
+                }),
+                _ => Err("Lens surfaces need a radius, thickness, index and aperture.")?,
+            }
+        }
+        if elements.is_empty() {
+            Err("Lens prescription has no surfaces.")?;
+        }
+        Ok(Self { elements })
+    }
+
+    fn ior_before(&self, index: usize) -> f32 {
+        match index {
+            0 => 1.0,
+            _ => self.elements[index - 1].ior,
+        }
+    }
+
+    // Distance of each surface's vertex from the film, with the film `film_distance` behind the
+    // last surface.
+    fn vertices(&self, film_distance: f32) -> Vec<f32> {
+        let last = self.elements.len() - 1;
+        let mut vertices = vec![film_distance; self.elements.len()];
+        for index in (0..last).rev() {
+            vertices[index] = vertices[index + 1] + self.elements[index].thickness;
+        }
+        vertices
+    }
+}
+
+// A region of the rear surface, in the plane of its vertex, through which light reaches a point
+// of the film on the positive x axis.
+#[derive(Copy, Clone)]
+struct Pupil {
+    min: (f32, f32),
+    max: (f32, f32),
+}
+
+impl Pupil {
+    fn area(&self) -> f32 {
+        (self.max.0 - self.min.0) * (self.max.1 - self.min.1)
+    }
+}
+
+// Rays traced from the film through each element of a real lens, which brings its distortion,
+// vignetting and bokeh along. The lens focuses by moving away from the film, so the field of view
+// changes with the focus distance. Brightness is scaled so that the centre of the image matches
+// the scene's radiance.
+//
+// Lens space has the film at the origin, facing the scene along +z, with lengths in millimetres.
+pub struct Realistic {
+    view: View,
+    system: LensSystem,
+    vertices: Vec<f32>,
+    film_size: (f32, f32),
+    pupils: Vec<Option<Pupil>>,
+    // Average light through the lens at the centre of the film, for a unit radiance.
+    reference: f32,
+}
+
+impl Realistic {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        view: View,
+        system: LensSystem,
+        sensor_width: f32,
+        aspect_ratio: f32,
+    ) -> Result<Arc<dyn Camera>, Box<dyn Error>> {
+        let mut camera = Self {
+            view,
+            vertices: system.vertices(system.elements[system.elements.len() - 1].thickness),
+            system,
+            film_size: (sensor_width, sensor_width / aspect_ratio),
+            pupils: Vec::new(),
+            reference: 1.0,
+        };
+        camera.focus(view.focus_distance * 1000.0)?;
+
+        let half_diagonal = (camera.film_size.0.hypot(camera.film_size.1)) / 2.0;
+        camera.pupils = (0..PUPIL_BINS)
+            .map(|bin| camera.pupil((bin as f32 + 0.5) / PUPIL_BINS as f32 * half_diagonal))
+            .collect();
+        camera.reference = camera.on_axis_light();
+        if camera.reference <= 0.0 {
+            Err("No light gets through the lens.")?;
+        }
+        Ok(Arc::new(camera))
+    }
+
+    // Moves the lens so that points `distance` in front of it are sharp.
+    fn focus(&mut self, distance: f32) -> Result<(), Box<dyn Error>> {
+        let front = &self.system.elements[0];
+        let height = 0.01 * front.aperture_radius;
+        let origin = Vector::new(0.0, 0.0, self.vertices[0] + distance);
+        let ray = Ray::new(origin, Vector::new(height, 0.0, self.vertices[0]) - origin);
+        let ray = self
+            .trace(ray, false)
+            .ok_or("Can't focus the lens at that distance.")?;
+
+        // Where the ray crosses the axis is where the film should be.
+        let (origin, direction) = (ray.origin(), ray.direction());
+        let image = origin[2] - origin[0] / direction[0] * direction[2];
+        let last = self.system.elements.len() - 1;
+        let film_distance = self.system.elements[last].thickness - image;
+        if !film_distance.is_finite() || film_distance <= 0.0 {
+            Err("Can't focus the lens at that distance.")?;
+        }
+        self.vertices = self.system.vertices(film_distance);
+        Ok(())
+    }
+
+    // Follows a ray through every surface, from the film towards the scene or the other way.
+    fn trace(&self, mut ray: Ray, from_film: bool) -> Option<Ray> {
+        let count = self.system.elements.len();
+        for step in 0..count {
+            let index = if from_film { count - 1 - step } else { step };
+            let (before, after) = (
+                self.system.ior_before(index),
+                self.system.elements[index].ior,
+            );
+            let (from, to) = if from_film {
+                (after, before)
+            } else {
+                (before, after)
+            };
+            ray = self.refract(index, &ray, from / to)?;
+        }
+        Some(ray)
+    }
+
+    fn refract(&self, index: usize, ray: &Ray, index_ratio: f32) -> Option<Ray> {
+        let element = &self.system.elements[index];
+        let vertex = self.vertices[index];
+        let (origin, direction) = (*ray.origin(), ray.direction().unit());
+
+        let (point, normal) = if element.radius == 0.0 {
+            let t = (vertex - origin[2]) / direction[2];
+            if !t.is_finite() || t <= 0.0 {
+                return None;
+            }
+            (origin + direction * t, Vector::new(0.0, 0.0, 1.0))
+        } else {
+            let centre = Vector::new(0.0, 0.0, vertex - element.radius);
+            let to_origin = origin - centre;
+            let b = to_origin.dot(&direction);
+            let c = to_origin.length_squared() - element.radius * element.radius;
+            let discriminant = b * b - c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            // Of the two intersections, the one on the same side of the centre as the vertex.
+            let root = discriminant.sqrt();
+            let t = [-b - root, -b + root].iter().copied().find(|&t| {
+                t > 1e-4 && (origin[2] + direction[2] * t - centre[2]) * element.radius > 0.0
+            })?;
+            let point = origin + direction * t;
+            (point, (point - centre).unit())
+        };
+
+        if point[0] * point[0] + point[1] * point[1]
+            > element.aperture_radius * element.aperture_radius
+        {
+            return None;
+        }
+        // Such as at the aperture stop, which light passes straight through.
+        if index_ratio == 1.0 {
+            return Some(Ray::new(point, direction));
+        }
+        let normal = if normal.dot(&direction) > 0.0 {
+            -normal
+        } else {
+            normal
+        };
+
+        let cosine = -direction.dot(&normal);
+        if index_ratio * index_ratio * (1.0 - cosine * cosine) > 1.0 {
+            return None;
+        }
+        Some(Ray::new(point, direction.refract(&normal, index_ratio)))
+    }
+
+    fn rear(&self) -> (f32, f32) {
+        let last = self.system.elements.len() - 1;
+        (
+            self.vertices[last],
+            self.system.elements[last].aperture_radius,
+        )
+    }
+
+    // Bounds of where on the rear surface light reaches the film `radius` from its centre, found
+    // by trying a grid of points a bit larger than the surface.
+    fn pupil(&self, radius: f32) -> Option<Pupil> {
+        let (z, aperture_radius) = self.rear();
+        let extent = 1.5 * aperture_radius;
+        let cell = 2.0 * extent / PUPIL_GRID as f32;
+        let film_point = Vector::new(radius, 0.0, 0.0);
+
+        let mut pupil: Option<Pupil> = None;
+        for j in 0..PUPIL_GRID {
+            for i in 0..PUPIL_GRID {
+                let x = -extent + (i as f32 + 0.5) * cell;
+                let y = -extent + (j as f32 + 0.5) * cell;
+                let ray = Ray::new(film_point, Vector::new(x, y, z) - film_point);
+                if self.trace(ray, true).is_none() {
+                    continue;
+                }
+                pupil = Some(match pupil {
+                    Some(pupil) => Pupil {
+                        min: (pupil.min.0.min(x), pupil.min.1.min(y)),
+                        max: (pupil.max.0.max(x), pupil.max.1.max(y)),
+                    },
+                    None => Pupil {
+                        min: (x, y),
+                        max: (x, y),
+                    },
+                });
+            }
+        }
+        // Grow by a cell, to cover what fell between the points.
+        pupil.map(|pupil| Pupil {
+            min: (pupil.min.0 - cell, pupil.min.1 - cell),
+            max: (pupil.max.0 + cell, pupil.max.1 + cell),
+        })
+    }
+
+    // Irradiance at the centre of the film from a unit radiance, integrated over the rear
+    // surface.
+    fn on_axis_light(&self) -> f32 {
+        let (z, aperture_radius) = self.rear();
+        let extent = 1.5 * aperture_radius;
+        let cell = 2.0 * extent / PUPIL_GRID as f32;
+        let film_point = Vector::new(0.0, 0.0, 0.0);
+
+        let mut total = 0.0;
+        for j in 0..PUPIL_GRID {
+            for i in 0..PUPIL_GRID {
+                let x = -extent + (i as f32 + 0.5) * cell;
+                let y = -extent + (j as f32 + 0.5) * cell;
+                let direction = Vector::new(x, y, z) - film_point;
+                if self.trace(Ray::new(film_point, direction), true).is_some() {
+                    total += direction.unit()[2].powi(4) / (z * z) * cell * cell;
+                }
+            }
+        }
+        total
+    }
+}
+
+impl Camera for Realistic {
+    fn ray_from(&self, pos: (f32, f32), sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        // The lens turns the image upside down, so the film is too.
+        let film = (
+            -(pos.0 - 0.5) * self.film_size.0,
+            -(pos.1 - 0.5) * self.film_size.1,
+        );
+        let radius = film.0.hypot(film.1);
+        let half_diagonal = self.film_size.0.hypot(self.film_size.1) / 2.0;
+        let bin = ((radius / half_diagonal * PUPIL_BINS as f32) as usize).min(PUPIL_BINS - 1);
+        let pupil = self.pupils[bin]?;
+
+        // Pupils are found along the x axis, so turn them to face the film point.
+        let (cos, sin) = if radius > 0.0 {
+            (film.0 / radius, film.1 / radius)
+        } else {
+            (1.0, 0.0)
+        };
+        let u = sampler.next_2d();
+        let x = pupil.min.0 + u.0 * (pupil.max.0 - pupil.min.0);
+        let y = pupil.min.1 + u.1 * (pupil.max.1 - pupil.min.1);
+        let (z, _) = self.rear();
+        let film_point = Vector::new(film.0, film.1, 0.0);
+        let direction = Vector::new(x * cos - y * sin, x * sin + y * cos, z) - film_point;
+        let ray = self.trace(Ray::new(film_point, direction), true)?;
+
+        let weight = direction.unit()[2].powi(4) / (z * z) * pupil.area() / self.reference;
+        let (origin, direction) = (ray.origin(), ray.direction());
+        let (front, scale) = (self.vertices[0], 0.001);
+        Some(CameraRay {
+            ray: Ray::new(
+                self.view.origin
+                    + self.view.direction(
+                        origin[0] * scale,
+                        origin[1] * scale,
+                        -(origin[2] - front) * scale,
+                    ),
+                self.view
+                    .direction(direction[0], direction[1], -direction[2]),
+            ),
+            weight: Vector::new(weight, weight, weight),
+        })
+    }
+
+    fn view(&self) -> &View {
+        &self.view
+    }
+
+    fn depth(&self, ray: &Ray, point: &Vector) -> f32 {
+        (*point - *ray.origin()).dot(&self.forward())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescriptions_are_parsed() {
+        let system = LensSystem::parse(
+            "# A single lens.\n\
+             50 5 1.5 20\n\
+             \n\
+             0 2 0 10 # Stop.\n\
+             inf 40 0 20\n\
+             -1e9 1 1 20\n",
+        )
+        .unwrap();
+        let radii: Vec<_> = system
+            .elements
+            .iter()
+            .map(|element| element.radius)
+            .collect();
+        assert_eq!(radii, [50.0, 0.0, 0.0, 0.0]);
+        assert_eq!(system.elements[1].ior, 1.0);
+        assert_eq!(system.elements[0].aperture_radius, 10.0);
+    }
+
+    #[test]
+    fn malformed_prescriptions_are_rejected() {
+        for text in &[
+            "",
+            "# Nothing.\n",
+            "50 5 1.5\n",
+            "50 5 1.5 20 1\n",
+            "50 five 1.5 20\n",
+        ] {
+            assert!(LensSystem::parse(text).is_err(), "{:?}", text);
+        }
+    }
+}
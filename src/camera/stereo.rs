@@ -0,0 +1,105 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::{Camera, CameraRay, View};
+use crate::sampler::Sampler;
+use crate::{Ray, Vector};
+
+// Where each eye's image goes on the film. The left eye is on the left, or on top.
+#[derive(Copy, Clone)]
+pub enum Layout {
+    SideBySide,
+    TopBottom,
+}
+
+impl FromStr for Layout {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "side-by-side" => Self::SideBySide,
+            "top-bottom" => Self::TopBottom,
+            _ => Err("Unknown stereo layout.")?,
+        })
+    }
+}
+
+// Two views of the scene through `camera`, with each eye's rays moved sideways by half the
+// interocular distance. Rays are sheared so that both eyes see the same point at the convergence
+// distance, while keeping the focus distance.
+//
+// For omni-directional stereo, the eyes are offset at right angles to each ray's horizontal
+// direction rather than to the view, as if the head turned to look along it. The offset shrinks
+// towards the poles, where there is no telling which way the head should face.
+pub struct Stereo {
+    camera: Arc<dyn Camera>,
+    layout: Layout,
+    interocular_distance: f32,
+    convergence_distance: f32,
+    omnidirectional: bool,
+}
+
+impl Stereo {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        camera: Arc<dyn Camera>,
+        layout: Layout,
+        interocular_distance: f32,
+        convergence_distance: f32,
+        omnidirectional: bool,
+    ) -> Arc<dyn Camera> {
+        Arc::new(Self {
+            camera,
+            layout,
+            interocular_distance,
+            convergence_distance,
+            omnidirectional,
+        })
+    }
+
+    // The eye's sideways direction and the direction it faces, for a ray going along `direction`.
+    fn orientation(&self, direction: &Vector) -> (Vector, Vector) {
+        let (right, up, backward) = self.view().basis;
+        if !self.omnidirectional {
+            return (right, -backward);
+        }
+
+        let direction = direction.unit();
+        let horizontal = direction - up * direction.dot(&up);
+        let side = horizontal.cross(&up);
+        if horizontal.near_zero() {
+            (side, horizontal)
+        } else {
+            (side, horizontal.unit())
+        }
+    }
+}
+
+impl Camera for Stereo {
+    fn ray_from(&self, pos: (f32, f32), sampler: &mut dyn Sampler) -> Option<CameraRay> {
+        let (left, pos) = match self.layout {
+            Layout::SideBySide => (pos.0 < 0.5, ((2.0 * pos.0).fract(), pos.1)),
+            Layout::TopBottom => (pos.1 >= 0.5, (pos.0, (2.0 * pos.1).fract())),
+        };
+        let eye = if left { -0.5 } else { 0.5 } * self.interocular_distance;
+
+        let CameraRay { ray, weight } = self.camera.ray_from(pos, sampler)?;
+        let (side, facing) = self.orientation(ray.direction());
+        let shear = ray.direction().dot(&facing) / self.convergence_distance;
+        Some(CameraRay {
+            ray: Ray::new(
+                *ray.origin() + side * eye,
+                *ray.direction() - side * (eye * shear),
+            ),
+            weight,
+        })
+    }
+
+    fn view(&self) -> &View {
+        self.camera.view()
+    }
+
+    fn depth(&self, ray: &Ray, point: &Vector) -> f32 {
+        self.camera.depth(ray, point)
+    }
+}
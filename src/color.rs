@@ -19,10 +19,29 @@ impl Color {
         }
     }
 
-    fn scaled(&self) -> Vector {
+    // Blue through green to red, as `t` goes from 0 to 1.
+    pub fn heatmap(t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        Self::new(t, 1.0 - (2.0 * t - 1.0).abs(), 1.0 - t)
+    }
+
+    // The average of all samples.
+    pub fn scaled(&self) -> Vector {
         self.value / self.samples as f32
     }
 
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // A color made of `samples` samples adding up to `sum`.
+    pub fn from_sum(sum: Vector, samples: usize) -> Self {
+        Self {
+            value: sum,
+            samples,
+        }
+    }
+
     fn bytes(&self) -> (u8, u8, u8) {
         let scaled = self.scaled();
         (
@@ -32,6 +51,14 @@ impl Color {
         )
     }
 
+    // Scales each channel of every sample.
+    pub fn weighted(&self, weight: &Vector) -> Self {
+        Self {
+            value: self.value * *weight,
+            samples: self.samples,
+        }
+    }
+
     pub fn add_samples(&mut self, other: &Color) {
         self.value += &other.value;
         self.samples += other.samples;
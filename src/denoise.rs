@@ -0,0 +1,173 @@
+use crate::film::{Aov, Film};
+use crate::Vector;
+
+// Edge-avoiding à-trous wavelet filter (Dammertz et al. 2010), with the colour weight scaled by
+// each pixel's standard deviation as in SVGF (Schied et al. 2017). Lighting is filtered with the
+// albedo divided out, so that texture detail survives.
+
+// The features the film must record for denoising.
+pub const FEATURES: [Aov; 3] = [Aov::Albedo, Aov::Normal, Aov::Depth];
+
+const ITERATIONS: usize = 5;
+const KERNEL: [f32; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+const NORMAL_POWER: i32 = 64;
+// Relative depth difference, per pixel apart, at which a neighbour's weight falls to 1/e.
+const DEPTH_SIGMA: f32 = 0.02;
+const LUMINANCE_SIGMA: f32 = 4.0;
+
+fn luminance(color: &Vector) -> f32 {
+    0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2]
+}
+
+struct Features {
+    normals: Vec<Vector>,
+    depths: Vec<f32>,
+}
+
+impl Features {
+    // How much pixel `q` should contribute to pixel `p`, `distance` pixels away.
+    fn weight(&self, p: usize, q: usize, distance: f32) -> f32 {
+        let (depth_p, depth_q) = (self.depths[p], self.depths[q]);
+        match (depth_p > 0.0, depth_q > 0.0) {
+            // Both see the background.
+            (false, false) => return 1.0,
+            (true, true) => {}
+            _ => return 0.0,
+        }
+
+        let normal = self.normals[p]
+            .dot(&self.normals[q])
+            .max(0.0)
+            .powi(NORMAL_POWER);
+        let depth = (-(depth_p - depth_q).abs() / (DEPTH_SIGMA * depth_p * distance)).exp();
+        normal * depth
+    }
+}
+
+// Variance blurred over a 3x3 neighbourhood, which is less noisy to steer by.
+fn blurred(variance: &[f32], (width, height): (usize, usize)) -> Vec<f32> {
+    let mut ret = vec![0.0; variance.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = 0.0;
+            let mut total = 0.0;
+            for dy in -1..=1_i32 {
+                for dx in -1..=1_i32 {
+                    let (qx, qy) = (x as i32 + dx, y as i32 + dy);
+                    if qx < 0 || qy < 0 || qx >= width as i32 || qy >= height as i32 {
+                        continue;
+                    }
+                    let weight = KERNEL[(dx + 2) as usize] * KERNEL[(dy + 2) as usize];
+                    sum += weight * variance[qy as usize * width + qx as usize];
+                    total += weight;
+                }
+            }
+            ret[y * width + x] = sum / total;
+        }
+    }
+    ret
+}
+
+// Returns the film's pixels with the noise filtered out, top row first.
+pub fn denoise(film: &Film) -> Vec<Vector> {
+    let (width, height) = film.window().size();
+    let albedo: Vec<_> = film
+        .aov(Aov::Albedo)
+        .into_iter()
+        .map(|albedo| {
+            // Pixels without an albedo, like the background, are filtered as they are.
+            if albedo.max_component() < 1e-3 {
+                Vector::new(1.0, 1.0, 1.0)
+            } else {
+                Vector::new(
+                    albedo[0].max(1e-3),
+                    albedo[1].max(1e-3),
+                    albedo[2].max(1e-3),
+                )
+            }
+        })
+        .collect();
+    let features = Features {
+        normals: film.aov(Aov::Normal),
+        depths: film.aov(Aov::Depth).iter().map(|depth| depth[0]).collect(),
+    };
+
+    let mut color: Vec<_> = film
+        .pixels()
+        .iter()
+        .zip(&albedo)
+        .map(|(pixel, albedo)| {
+            let value = if pixel.samples() == 0 {
+                Vector::new(0.0, 0.0, 0.0)
+            } else {
+                pixel.scaled()
+            };
+            Vector::new(
+                value[0] / albedo[0],
+                value[1] / albedo[1],
+                value[2] / albedo[2],
+            )
+        })
+        .collect();
+    let mut variance: Vec<_> = film
+        .variance()
+        .iter()
+        .zip(&albedo)
+        .map(|(variance, albedo)| luminance(variance) / luminance(albedo).powi(2))
+        .collect();
+
+    for iteration in 0..ITERATIONS {
+        let step = 1 << iteration;
+        let deviation = blurred(&variance, (width, height));
+        let mut next_color = color.clone();
+        let mut next_variance = variance.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let p = y * width + x;
+                let luminance_p = luminance(&color[p]);
+                let scale = LUMINANCE_SIGMA * deviation[p].sqrt() + 1e-4;
+
+                let mut sum = Vector::new(0.0, 0.0, 0.0);
+                let mut variance_sum = 0.0;
+                let mut total = 0.0;
+                for (j, ky) in KERNEL.iter().enumerate() {
+                    for (i, kx) in KERNEL.iter().enumerate() {
+                        let dx = (i as i32 - 2) * step;
+                        let dy = (j as i32 - 2) * step;
+                        let (qx, qy) = (x as i32 + dx, y as i32 + dy);
+                        if qx < 0 || qy < 0 || qx >= width as i32 || qy >= height as i32 {
+                            continue;
+                        }
+                        let q = qy as usize * width + qx as usize;
+
+                        let weight = if p == q {
+                            kx * ky
+                        } else {
+                            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                            let similarity =
+                                (-(luminance_p - luminance(&color[q])).abs() / scale).exp();
+                            kx * ky * similarity * features.weight(p, q, distance)
+                        };
+                        sum += &(color[q] * weight);
+                        variance_sum += weight * weight * variance[q];
+                        total += weight;
+                    }
+                }
+
+                next_color[p] = sum / total;
+                next_variance[p] = variance_sum / (total * total);
+            }
+        }
+
+        color = next_color;
+        variance = next_variance;
+    }
+
+    color
+        .iter()
+        .zip(&albedo)
+        .map(|(color, albedo)| *color * *albedo)
+        .collect()
+}
@@ -0,0 +1,721 @@
+use std::io::{self, BufRead, Read, Write};
+use std::ops::Range;
+use std::str::FromStr;
+
+use crate::filter::Filter;
+use crate::{Color, Vector};
+
+// Arbitrary output variables, written alongside the image for compositing.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Aov {
+    Albedo,
+    // Shading normal, facing the camera.
+    Normal,
+    // Along the camera's forward axis, zero where nothing was hit.
+    Depth,
+    Position,
+    DiffuseDirect,
+    DiffuseIndirect,
+    SpecularDirect,
+    SpecularIndirect,
+    // Light seen without any bounce, including the background.
+    Emission,
+    SampleCount,
+    // Sample counts relative to the most sampled pixel, coloured from blue to red.
+    SampleHeatmap,
+}
+
+const AOV_COUNT: usize = 11;
+
+impl Aov {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Albedo => "albedo",
+            Self::Normal => "normal",
+            Self::Depth => "depth",
+            Self::Position => "position",
+            Self::DiffuseDirect => "diffuse-direct",
+            Self::DiffuseIndirect => "diffuse-indirect",
+            Self::SpecularDirect => "specular-direct",
+            Self::SpecularIndirect => "specular-indirect",
+            Self::Emission => "emission",
+            Self::SampleCount => "samples",
+            Self::SampleHeatmap => "samples-heatmap",
+        }
+    }
+}
+
+impl FromStr for Aov {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "albedo" => Self::Albedo,
+            "normal" => Self::Normal,
+            "depth" => Self::Depth,
+            "position" => Self::Position,
+            "diffuse-direct" => Self::DiffuseDirect,
+            "diffuse-indirect" => Self::DiffuseIndirect,
+            "specular-direct" => Self::SpecularDirect,
+            "specular-indirect" => Self::SpecularIndirect,
+            "emission" => Self::Emission,
+            "samples" => Self::SampleCount,
+            "samples-heatmap" => Self::SampleHeatmap,
+            _ => Err("Unknown AOV.")?,
+        })
+    }
+}
+
+// AOV values for a single camera sample. Integrators fill in whichever they can.
+#[derive(Copy, Clone)]
+pub struct AovSample([Vector; AOV_COUNT]);
+
+impl AovSample {
+    pub fn get(&self, aov: Aov) -> &Vector {
+        &self.0[aov as usize]
+    }
+
+    pub fn set(&mut self, aov: Aov, value: Vector) {
+        self.0[aov as usize] = value;
+    }
+
+    pub fn add(&mut self, aov: Aov, value: &Vector) {
+        self.0[aov as usize] += value;
+    }
+
+    pub fn merge(&mut self, other: &AovSample) {
+        self.0
+            .iter_mut()
+            .zip(other.0.iter())
+            .for_each(|(a, b)| *a += b);
+    }
+
+    pub fn scaled(&self, factor: f32) -> AovSample {
+        let mut ret = *self;
+        ret.0.iter_mut().for_each(|value| *value = *value * factor);
+        ret
+    }
+}
+
+impl Default for AovSample {
+    fn default() -> Self {
+        Self([Vector::new(0.0, 0.0, 0.0); AOV_COUNT])
+    }
+}
+
+// Units of the fixed point values in `ExactSum`.
+const EXACT_SCALE: f64 = (1u64 << 24) as f64;
+
+// Saturates for infinite or huge values, and takes NaN as zero.
+fn to_fixed(value: f32) -> i64 {
+    (value as f64 * EXACT_SCALE).round() as i64
+}
+
+fn from_fixed(value: i64) -> f32 {
+    (value as f64 / EXACT_SCALE) as f32
+}
+
+// Sum of vectors in fixed point, which unlike floating point comes out the same whatever order the
+// terms are added in. Sums saturate rather than wrap around, so a stray infinite sample leaves a
+// pixel bright rather than black.
+#[derive(Copy, Clone, Default)]
+pub struct ExactSum([i64; 3]);
+
+impl ExactSum {
+    pub fn add(&mut self, value: &Vector) {
+        for channel in 0..3 {
+            self.0[channel] = self.0[channel].saturating_add(to_fixed(value[channel]));
+        }
+    }
+
+    pub fn merge(&mut self, other: &ExactSum) {
+        for channel in 0..3 {
+            self.0[channel] = self.0[channel].saturating_add(other.0[channel]);
+        }
+    }
+
+    pub fn value(&self) -> Vector {
+        Vector::new(
+            from_fixed(self.0[0]),
+            from_fixed(self.0[1]),
+            from_fixed(self.0[2]),
+        )
+    }
+}
+
+// Filtered samples of a pixel. Samples can come from neighbouring pixels, sampled on other
+// threads, so this is exact too.
+#[derive(Copy, Clone, Default)]
+struct Filtered {
+    sum: ExactSum,
+    weight: i64,
+}
+
+// A rectangle of pixels, counted from the bottom left like film coordinates. `max` is left out.
+#[derive(Copy, Clone)]
+pub struct Window {
+    pub min: (usize, usize),
+    pub max: (usize, usize),
+}
+
+impl Window {
+    pub fn size(&self) -> (usize, usize) {
+        (self.max.0 - self.min.0, self.max.1 - self.min.1)
+    }
+
+    pub fn contains(&self, pixel: (usize, usize)) -> bool {
+        (self.min.0..self.max.0).contains(&pixel.0) && (self.min.1..self.max.1).contains(&pixel.1)
+    }
+}
+
+// Accumulated samples for every pixel of the window, stored top row first. `pixels` holds plain
+// averages of the samples taken in each pixel, for sample counts and variance, while the image
+// comes from `filtered`.
+pub struct Film {
+    // Of the whole image, which sets the framing.
+    dimensions: (usize, usize),
+    window: Window,
+    filter: Filter,
+    pixels: Vec<Color>,
+    filtered: Vec<Filtered>,
+    // Sum of the squares of every sample, for estimating variance.
+    squares: Vec<Vector>,
+    // Light added to pixels other than the one being sampled, which may come from any thread.
+    splats: Vec<ExactSum>,
+    // Samples taken over the whole sample window, which splats are spread over.
+    samples: usize,
+    aovs: Vec<(Aov, Vec<Color>)>,
+    // Samples per pixel each row of the image has been rendered with, for carrying on later.
+    rendered: Vec<usize>,
+}
+
+impl Film {
+    // Holds only the pixels of an image of `dimensions` within `window`, as they would be in the
+    // whole image.
+    pub fn new(dimensions: (usize, usize), window: Window) -> Self {
+        let (width, height) = window.size();
+        Self {
+            dimensions,
+            window,
+            filter: Default::default(),
+            pixels: [Default::default()].repeat(width * height),
+            filtered: vec![Default::default(); width * height],
+            squares: vec![Vector::new(0.0, 0.0, 0.0); width * height],
+            splats: vec![Default::default(); width * height],
+            samples: 0,
+            aovs: Vec::new(),
+            rendered: vec![0; dimensions.1],
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    // An empty film with the same size, filter and AOVs.
+    pub fn cleared(&self) -> Self {
+        let aovs: Vec<_> = self.aovs.iter().map(|(aov, _)| *aov).collect();
+        Self::new(self.dimensions, self.window)
+            .with_filter(self.filter.clone())
+            .with_aovs(&aovs)
+    }
+
+    // Also accumulates `aovs`. Sample counts come from the image itself, so need no buffer.
+    pub fn with_aovs(mut self, aovs: &[Aov]) -> Self {
+        let size = self.pixels.len();
+        self.aovs = aovs
+            .iter()
+            .filter(|&&aov| aov != Aov::SampleCount && aov != Aov::SampleHeatmap)
+            .map(|&aov| (aov, [Default::default()].repeat(size)))
+            .collect();
+        self
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        self.dimensions
+    }
+
+    pub fn window(&self) -> Window {
+        self.window
+    }
+
+    // Pixels to take samples in. Besides the window, these include pixels just outside it whose
+    // samples the filter spreads into it, so that its edges come out as in the whole image.
+    pub fn sample_window(&self) -> Window {
+        if self.filter.importance_sampled() {
+            return self.window;
+        }
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as usize;
+        Window {
+            min: (
+                self.window.min.0.saturating_sub(margin),
+                self.window.min.1.saturating_sub(margin),
+            ),
+            max: (
+                (self.window.max.0 + margin).min(self.dimensions.0),
+                (self.window.max.1 + margin).min(self.dimensions.1),
+            ),
+        }
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn rendered(&self, row: usize) -> usize {
+        self.rendered[row]
+    }
+
+    pub fn set_rendered(&mut self, rows: Range<usize>, samples_per_pixel: usize) {
+        self.rendered[rows]
+            .iter_mut()
+            .for_each(|rendered| *rendered = samples_per_pixel);
+    }
+
+    fn index(&self, pixel: (usize, usize)) -> usize {
+        let (width, _) = self.window.size();
+        (self.window.max.1 - 1 - pixel.1) * width + pixel.0 - self.window.min.0
+    }
+
+    // A sample taken for `pixel`, at `offset` pixels from its centre and weighted by `weight` as
+    // well as the filter. `pixel` is counted from the bottom left, like film coordinates.
+    pub fn add_sample(
+        &mut self,
+        pixel: (usize, usize),
+        offset: (f32, f32),
+        weight: f32,
+        color: &Color,
+    ) {
+        self.samples += 1;
+        let inside = self.window.contains(pixel);
+        if inside {
+            let i = self.index(pixel);
+            self.pixels[i].add_samples(color);
+            self.squares[i] += &(**color * **color);
+        }
+
+        if self.filter.importance_sampled() {
+            if inside {
+                let i = self.index(pixel);
+                self.filtered[i].sum.add(&(**color * weight));
+                self.filtered[i].weight = self.filtered[i].weight.saturating_add(to_fixed(weight));
+            }
+            return;
+        }
+
+        // Every pixel whose centre is within the filter's radius of the sample.
+        let radius = self.filter.radius();
+        let position = (
+            pixel.0 as f32 + 0.5 + offset.0,
+            pixel.1 as f32 + 0.5 + offset.1,
+        );
+        let low = |x: f32, min: usize| (x - radius - 0.5).ceil().max(min as f32) as usize;
+        let high = |x: f32, max: usize| ((x + radius - 0.5).floor() as usize).min(max - 1);
+        let window = self.window;
+        for y in low(position.1, window.min.1)..=high(position.1, window.max.1) {
+            for x in low(position.0, window.min.0)..=high(position.0, window.max.0) {
+                let offset = (position.0 - (x as f32 + 0.5), position.1 - (y as f32 + 0.5));
+                let filter_weight = self.filter.evaluate(offset);
+                if filter_weight != 0.0 {
+                    let i = self.index((x, y));
+                    let weight = weight * filter_weight;
+                    self.filtered[i].sum.add(&(**color * weight));
+                    self.filtered[i].weight =
+                        self.filtered[i].weight.saturating_add(to_fixed(weight));
+                }
+            }
+        }
+    }
+
+    pub fn add_aovs(&mut self, pixel: (usize, usize), sample: &AovSample) {
+        if !self.window.contains(pixel) {
+            return;
+        }
+        let i = self.index(pixel);
+        for (aov, buffer) in &mut self.aovs {
+            buffer[i].add_samples(&(*sample.get(*aov)).into());
+        }
+    }
+
+    // Adds light to whichever pixel contains the film coordinate, without counting it as a sample.
+    pub fn splat(&mut self, uv: (f32, f32), value: &Vector) {
+        if !(0.0..1.0).contains(&uv.0) || !(0.0..1.0).contains(&uv.1) {
+            return;
+        }
+        let pixel = (
+            (uv.0 * self.dimensions.0 as f32) as usize,
+            (uv.1 * self.dimensions.1 as f32) as usize,
+        );
+        if self.window.contains(pixel) {
+            let i = self.index(pixel);
+            self.splats[i].add(value);
+        }
+    }
+
+    // Only exact when each pixel was sampled on one of the films, which keeps renders the same
+    // however they are split between threads.
+    pub fn merge(&mut self, other: &Film) {
+        self.pixels
+            .iter_mut()
+            .zip(&other.pixels)
+            .for_each(|(a, b)| a.add_samples(b));
+        self.squares
+            .iter_mut()
+            .zip(&other.squares)
+            .for_each(|(a, b)| *a += b);
+        self.filtered
+            .iter_mut()
+            .zip(&other.filtered)
+            .for_each(|(a, b)| {
+                a.sum.merge(&b.sum);
+                a.weight = a.weight.saturating_add(b.weight);
+            });
+        self.splats
+            .iter_mut()
+            .zip(&other.splats)
+            .for_each(|(a, b)| a.merge(b));
+        self.samples += other.samples;
+        for ((_, buffer), (_, other)) in self.aovs.iter_mut().zip(&other.aovs) {
+            buffer
+                .iter_mut()
+                .zip(other)
+                .for_each(|(a, b)| a.add_samples(b));
+        }
+        self.rendered
+            .iter_mut()
+            .zip(&other.rendered)
+            .for_each(|(a, b)| *a = (*a).max(*b));
+    }
+
+    pub fn pixels(&self) -> Vec<Color> {
+        // Light is only traced from the pixels sampled, so when cropped, splats make up for the
+        // rest of the image.
+        let (width, height) = self.sample_window().size();
+        let coverage = (self.dimensions.0 * self.dimensions.1) as f32 / (width * height) as f32;
+        // Splats are spread over the samples, like the light tracing they come from. Light lands
+        // anywhere whichever pixel it was traced for, so this is the mean over the sample window
+        // rather than each pixel's own count, which varies when sampling adaptively or stopping
+        // partway through a pass.
+        let samples_per_pixel = self.samples as f32 / (width * height) as f32;
+        self.filtered
+            .iter()
+            .zip(&self.splats)
+            .map(|(filtered, splat)| {
+                let mut ret = Vector::new(0.0, 0.0, 0.0);
+                if filtered.weight != 0 {
+                    ret += &(filtered.sum.value() / from_fixed(filtered.weight));
+                }
+                if self.samples > 0 {
+                    ret += &(splat.value() / samples_per_pixel * coverage);
+                }
+                ret.into()
+            })
+            .collect()
+    }
+
+    // Variance of each pixel's mean, per channel.
+    pub fn variance(&self) -> Vec<Vector> {
+        self.pixels
+            .iter()
+            .zip(&self.squares)
+            .map(|(pixel, squares)| {
+                if pixel.samples() < 2 {
+                    return Vector::new(0.0, 0.0, 0.0);
+                }
+                let samples = pixel.samples() as f32;
+                let mean = pixel.scaled();
+                let variance = *squares / samples - mean * mean;
+                Vector::new(
+                    variance[0].max(0.0),
+                    variance[1].max(0.0),
+                    variance[2].max(0.0),
+                ) / (samples - 1.0)
+            })
+            .collect()
+    }
+
+    // Averaged values of an AOV, top row first.
+    pub fn aov(&self, aov: Aov) -> Vec<Vector> {
+        let average = |color: &Color| {
+            if color.samples() == 0 {
+                Vector::new(0.0, 0.0, 0.0)
+            } else {
+                color.scaled()
+            }
+        };
+
+        match aov {
+            Aov::SampleCount => {
+                return self
+                    .pixels
+                    .iter()
+                    .map(|pixel| {
+                        let samples = pixel.samples() as f32;
+                        Vector::new(samples, samples, samples)
+                    })
+                    .collect();
+            }
+            Aov::SampleHeatmap => {
+                let max = self.pixels.iter().map(Color::samples).max().unwrap_or(0);
+                return self
+                    .pixels
+                    .iter()
+                    .map(|pixel| *Color::heatmap(pixel.samples() as f32 / max.max(1) as f32))
+                    .collect();
+            }
+            _ => {}
+        }
+        self.aovs
+            .iter()
+            .find(|(other, _)| *other == aov)
+            .map(|(_, buffer)| buffer.iter().map(average).collect())
+            .unwrap_or_else(|| vec![Vector::new(0.0, 0.0, 0.0); self.pixels.len()])
+    }
+
+    // Writes an AOV as a little endian PFM image, which keeps the values linear and unclamped.
+    pub fn write_aov<W: Write>(&self, aov: Aov, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "PF")?;
+        let (width, height) = self.window.size();
+        writeln!(writer, "{} {}", width, height)?;
+        writeln!(writer, "-1.0")?;
+
+        // PFM rows go from the bottom up.
+        let values = self.aov(aov);
+        for row in values.chunks(width).rev() {
+            for value in row {
+                for channel in 0..3 {
+                    writer.write_all(&value[channel].to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Saves everything accumulated so far, to be carried on with by `read_checkpoint`.
+    pub fn write_checkpoint<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writeln!(writer, "{}", self.checkpoint_header())?;
+        for rendered in &self.rendered {
+            writer.write_all(&(*rendered as u64).to_le_bytes())?;
+        }
+        writer.write_all(&(self.samples as u64).to_le_bytes())?;
+        for i in 0..self.pixels.len() {
+            write_color(writer, &self.pixels[i])?;
+            write_exact(writer, &self.filtered[i].sum)?;
+            writer.write_all(&self.filtered[i].weight.to_le_bytes())?;
+            write_vector(writer, &self.squares[i])?;
+            write_exact(writer, &self.splats[i])?;
+        }
+        for (_, buffer) in &self.aovs {
+            for color in buffer {
+                write_color(writer, color)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Replaces what the film holds with a checkpoint, which should be of a film set up the same
+    // way.
+    pub fn read_checkpoint<R: BufRead>(&mut self, reader: &mut R) -> io::Result<()> {
+        let mut header = String::new();
+        reader.read_line(&mut header)?;
+        if header.trim_end_matches('\n') != self.checkpoint_header() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "checkpoint is of a differently sized image, crop or set of AOVs",
+            ));
+        }
+        for rendered in &mut self.rendered {
+            *rendered = read_u64(reader)? as usize;
+        }
+        self.samples = read_u64(reader)? as usize;
+        for i in 0..self.pixels.len() {
+            self.pixels[i] = read_color(reader)?;
+            self.filtered[i].sum = read_exact(reader)?;
+            self.filtered[i].weight = read_u64(reader)? as i64;
+            self.squares[i] = read_vector(reader)?;
+            self.splats[i] = read_exact(reader)?;
+        }
+        for (_, buffer) in &mut self.aovs {
+            for color in buffer {
+                *color = read_color(reader)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Identifies films with the same layout.
+    fn checkpoint_header(&self) -> String {
+        let aovs: Vec<_> = self.aovs.iter().map(|(aov, _)| aov.name()).collect();
+        format!(
+            "checkpoint {} {} {} {} {} {} {}",
+            self.dimensions.0,
+            self.dimensions.1,
+            self.window.min.0,
+            self.window.min.1,
+            self.window.max.0,
+            self.window.max.1,
+            aovs.join(",")
+        )
+    }
+}
+
+// Checkpoints are little endian.
+
+fn write_vector<W: Write>(writer: &mut W, value: &Vector) -> io::Result<()> {
+    for channel in 0..3 {
+        writer.write_all(&value[channel].to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn write_color<W: Write>(writer: &mut W, color: &Color) -> io::Result<()> {
+    write_vector(writer, color)?;
+    writer.write_all(&(color.samples() as u64).to_le_bytes())
+}
+
+fn write_exact<W: Write>(writer: &mut W, sum: &ExactSum) -> io::Result<()> {
+    for channel in 0..3 {
+        writer.write_all(&sum.0[channel].to_le_bytes())?;
+    }
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_vector<R: Read>(reader: &mut R) -> io::Result<Vector> {
+    let mut channels = [0.0; 3];
+    for channel in &mut channels {
+        let mut bytes = [0; 4];
+        reader.read_exact(&mut bytes)?;
+        *channel = f32::from_le_bytes(bytes);
+    }
+    Ok(Vector::new(channels[0], channels[1], channels[2]))
+}
+
+fn read_color<R: Read>(reader: &mut R) -> io::Result<Color> {
+    let sum = read_vector(reader)?;
+    Ok(Color::from_sum(sum, read_u64(reader)? as usize))
+}
+
+fn read_exact<R: Read>(reader: &mut R) -> io::Result<ExactSum> {
+    let mut sum = ExactSum::default();
+    for channel in &mut sum.0 {
+        *channel = read_u64(reader)? as i64;
+    }
+    Ok(sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::Shape;
+
+    #[test]
+    fn exact_sums_saturate_on_huge_values() {
+        let mut sum = ExactSum::default();
+        sum.add(&Vector::new(f32::INFINITY, 1e30, 1.0));
+        sum.add(&Vector::new(f32::INFINITY, 1e30, 1.0));
+        let mut merged = sum;
+        merged.merge(&sum);
+
+        for value in &[sum.value(), merged.value()] {
+            assert!(value[0] > 1e11);
+            assert!(value[1] > 1e11);
+        }
+        assert_eq!(sum.value()[2], 2.0);
+        assert_eq!(merged.value()[2], 4.0);
+    }
+
+    // A render stopped partway through a pass, as with --time-limit, leaves the rows it reached
+    // with a sample more than the rest. Light traced from the camera lands evenly across the
+    // image regardless, so splats must not come out brighter in the rows sampled less.
+    #[test]
+    fn splats_have_no_bands_when_stopped_partway_through_a_pass() {
+        let (width, height) = (4, 4);
+        let window = Window {
+            min: (0, 0),
+            max: (width, height),
+        };
+        let mut film = Film::new((width, height), window);
+        let black = Color::new(0.0, 0.0, 0.0);
+        for y in 0..height {
+            for x in 0..width {
+                let samples = if y < height / 2 { 2 } else { 1 };
+                for _ in 0..samples {
+                    film.add_sample((x, y), (0.0, 0.0), 1.0, &black);
+                }
+                let uv = (
+                    (x as f32 + 0.5) / width as f32,
+                    (y as f32 + 0.5) / height as f32,
+                );
+                film.splat(uv, &Vector::new(1.0, 1.0, 1.0));
+            }
+        }
+
+        let pixels = film.pixels();
+        for pixel in &pixels {
+            assert_eq!(pixel[0], pixels[0][0]);
+        }
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let window = Window {
+            min: (1, 1),
+            max: (5, 3),
+        };
+        let aovs = [Aov::Albedo, Aov::Depth];
+        let mut film = Film::new((6, 4), window)
+            .with_filter(Filter::new(Shape::Gaussian, 1.5, false))
+            .with_aovs(&aovs);
+        let sample_window = film.sample_window();
+        let mut aov = AovSample::default();
+        for y in sample_window.min.1..sample_window.max.1 {
+            for x in sample_window.min.0..sample_window.max.0 {
+                let value = (x * 7 + y) as f32 / 10.0;
+                film.add_sample((x, y), (0.2, -0.3), 0.9, &Color::new(value, 0.5, 1.0));
+                film.splat((value % 1.0, 0.4), &Vector::new(0.1, value, 0.3));
+                aov.set(Aov::Depth, Vector::new(value, value, value));
+                film.add_aovs((x, y), &aov);
+            }
+        }
+        film.set_rendered(1..3, 2);
+
+        let mut checkpoint = Vec::new();
+        film.write_checkpoint(&mut checkpoint).unwrap();
+        let mut resumed = film.cleared();
+        resumed.read_checkpoint(&mut &checkpoint[..]).unwrap();
+
+        let bits = |pixels: Vec<Vector>| -> Vec<u32> {
+            pixels
+                .iter()
+                .flat_map(|pixel| (0..3).map(move |channel| pixel[channel].to_bits()))
+                .collect()
+        };
+        let image = |film: &Film| bits(film.pixels().into_iter().map(|c| *c).collect());
+        assert_eq!(image(&resumed), image(&film));
+        assert_eq!(bits(resumed.variance()), bits(film.variance()));
+        for &aov in &aovs {
+            assert_eq!(bits(resumed.aov(aov)), bits(film.aov(aov)));
+        }
+        for row in 0..4 {
+            assert_eq!(resumed.rendered(row), film.rendered(row));
+        }
+
+        let other = Window {
+            min: (0, 1),
+            max: (5, 3),
+        };
+        let mut cropped = Film::new((6, 4), other).with_aovs(&aovs);
+        assert!(cropped.read_checkpoint(&mut &checkpoint[..]).is_err());
+        let mut without_aovs = Film::new((6, 4), window);
+        assert!(without_aovs.read_checkpoint(&mut &checkpoint[..]).is_err());
+        let truncated = &checkpoint[..checkpoint.len() - 1];
+        assert!(film.cleared().read_checkpoint(&mut &truncated[..]).is_err());
+    }
+}
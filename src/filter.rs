@@ -0,0 +1,231 @@
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+// Bins in the table used to importance sample a filter.
+const TABLE_SIZE: usize = 64;
+
+#[derive(Copy, Clone)]
+pub enum Shape {
+    Box,
+    Tent,
+    Gaussian,
+    // With B = C = 1/3, as recommended by Mitchell and Netravali (1988).
+    Mitchell,
+    // Sinc windowed by a sinc as wide as the radius.
+    Lanczos,
+}
+
+impl Shape {
+    pub fn default_radius(&self) -> f32 {
+        match self {
+            Self::Box => 0.5,
+            Self::Tent => 1.0,
+            Self::Gaussian => 1.5,
+            Self::Mitchell => 2.0,
+            Self::Lanczos => 3.0,
+        }
+    }
+
+    // One dimensional profile, for offsets within `radius` of the centre.
+    fn evaluate(&self, x: f32, radius: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Box => 1.0,
+            Self::Tent => radius - x,
+            Self::Gaussian => {
+                let sigma = radius / 3.0;
+                let gaussian = |x: f32| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(radius)
+            }
+            Self::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * x / radius;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x.powi(3)
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                }
+            }
+            Self::Lanczos => sinc(x) * sinc(x / radius),
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-5 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+impl FromStr for Shape {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "box" => Self::Box,
+            "tent" => Self::Tent,
+            "gaussian" => Self::Gaussian,
+            "mitchell" => Self::Mitchell,
+            "lanczos" => Self::Lanczos,
+            _ => Err("Unknown filter.")?,
+        })
+    }
+}
+
+// How samples are weighted into pixels. Either each sample is splatted into every pixel within
+// `radius` of it, weighted by the filter, or sample positions are importance sampled from the
+// filter so that each only counts towards its own pixel (Ernst et al. 2006).
+#[derive(Clone)]
+pub struct Filter {
+    shape: Shape,
+    radius: f32,
+    importance_sampled: bool,
+    // Cumulative distribution of the absolute filter profile over `-radius..radius`.
+    cdf: Vec<f32>,
+    // Divides the profile so that it integrates to 1.
+    integral: f32,
+}
+
+impl Filter {
+    pub fn new(shape: Shape, radius: f32, importance_sampled: bool) -> Self {
+        let mut cdf = Vec::with_capacity(TABLE_SIZE + 1);
+        cdf.push(0.0);
+        let mut integral = 0.0;
+        for bin in 0..TABLE_SIZE {
+            let x = radius * (2.0 * (bin as f32 + 0.5) / TABLE_SIZE as f32 - 1.0);
+            let value = shape.evaluate(x, radius);
+            cdf.push(cdf[bin] + value.abs());
+            integral += value * 2.0 * radius / TABLE_SIZE as f32;
+        }
+        let total = cdf[TABLE_SIZE];
+        if total > 0.0 {
+            cdf.iter_mut().for_each(|value| *value /= total);
+        }
+
+        Self {
+            shape,
+            radius,
+            importance_sampled,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    pub fn importance_sampled(&self) -> bool {
+        self.importance_sampled
+    }
+
+    // Weight of a sample `offset` pixels from a pixel's centre.
+    pub fn evaluate(&self, offset: (f32, f32)) -> f32 {
+        let profile = |x: f32| {
+            if (-self.radius..self.radius).contains(&x) {
+                self.shape.evaluate(x, self.radius) / self.integral
+            } else {
+                0.0
+            }
+        };
+        profile(offset.0) * profile(offset.1)
+    }
+
+    // Picks an offset from the centre of a pixel to sample at, along with the sample's weight.
+    pub fn sample(&self, u: (f32, f32)) -> ((f32, f32), f32) {
+        if !self.importance_sampled {
+            return ((u.0 - 0.5, u.1 - 0.5), 1.0);
+        }
+        let (x, x_weight) = self.sample_1d(u.0);
+        let (y, y_weight) = self.sample_1d(u.1);
+        ((x, y), x_weight * y_weight)
+    }
+
+    fn sample_1d(&self, u: f32) -> (f32, f32) {
+        let bin = self
+            .cdf
+            .partition_point(|&value| value <= u)
+            .clamp(1, TABLE_SIZE)
+            - 1;
+        let width = self.cdf[bin + 1] - self.cdf[bin];
+        let within = if width > 0.0 {
+            (u - self.cdf[bin]) / width
+        } else {
+            0.5
+        };
+        let x = self.radius * (2.0 * (bin as f32 + within) / TABLE_SIZE as f32 - 1.0);
+
+        // Close to the sign of the filter, but exact where the table is coarse.
+        let pdf = width * TABLE_SIZE as f32 / (2.0 * self.radius);
+        let value = self.shape.evaluate(x, self.radius) / self.integral;
+        (x, if pdf > 0.0 { value / pdf } else { 0.0 })
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(Shape::Box, Shape::Box.default_radius(), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: [Shape; 5] = [
+        Shape::Box,
+        Shape::Tent,
+        Shape::Gaussian,
+        Shape::Mitchell,
+        Shape::Lanczos,
+    ];
+
+    #[test]
+    fn filters_integrate_to_one() {
+        for shape in &SHAPES {
+            let filter = Filter::new(*shape, shape.default_radius(), false);
+            let steps = 400;
+            let step = 2.0 * filter.radius() / steps as f32;
+            let mut integral = 0.0;
+            for i in 0..steps {
+                for j in 0..steps {
+                    let x = -filter.radius() + (i as f32 + 0.5) * step;
+                    let y = -filter.radius() + (j as f32 + 0.5) * step;
+                    integral += filter.evaluate((x, y)) * step * step;
+                }
+            }
+            assert!((integral - 1.0).abs() < 1e-2, "{}", integral);
+        }
+    }
+
+    #[test]
+    fn importance_sampling_stays_within_the_radius_and_is_unbiased() {
+        for shape in &SHAPES {
+            let filter = Filter::new(*shape, shape.default_radius(), true);
+            let steps = 1000;
+            let mut total = 0.0;
+            for i in 0..steps {
+                for j in 0..steps {
+                    let u = (
+                        (i as f32 + 0.5) / steps as f32,
+                        (j as f32 + 0.5) / steps as f32,
+                    );
+                    let ((x, y), weight) = filter.sample(u);
+                    assert!(x.abs() <= filter.radius() && y.abs() <= filter.radius());
+                    total += weight;
+                }
+            }
+            let mean = total / (steps * steps) as f32;
+            assert!((mean - 1.0).abs() < 1e-2, "{}", mean);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::ops::Range;
+
+use indicatif::ProgressBar;
+
+use super::{sample_pixel, Integrator};
+use crate::camera::Camera;
+use crate::film::Film;
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::Vector;
+
+// No pixel takes more than this many times its share of the budget.
+const MAX_SHARE: usize = 8;
+// Below this, relative error is measured against it instead, so that dark pixels can converge.
+const MIN_LUMINANCE: f32 = 1e-2;
+
+// Running mean and variance of a pixel's luminance (Welford 1962).
+#[derive(Copy, Clone, Default)]
+struct Welford {
+    count: usize,
+    mean: f32,
+    // Sum of squared differences from the mean.
+    m2: f32,
+}
+
+impl Welford {
+    fn add(&mut self, color: &Vector) {
+        let value = 0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2];
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f32;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    // Standard error of the mean, relative to the mean.
+    fn relative_error(&self) -> f32 {
+        if self.count < 2 {
+            return f32::INFINITY;
+        }
+        let variance = self.m2 / (self.count - 1) as f32;
+        (variance / self.count as f32).sqrt() / self.mean.max(MIN_LUMINANCE)
+    }
+}
+
+// Spends a fixed total number of samples on a block of rows, giving more to pixels which are still
+// noisy.
+pub struct Scheduler {
+    columns: Range<usize>,
+    rows: Range<usize>,
+    // Where sample numbers start, after those of the samples per pixel already taken. Any pixel
+    // may have taken up to `MAX_SHARE` times as many.
+    first_sample: usize,
+    samples_per_pixel: usize,
+    threshold: f32,
+    pixels: Vec<Welford>,
+}
+
+impl Scheduler {
+    pub fn new(
+        columns: Range<usize>,
+        rows: Range<usize>,
+        rendered: usize,
+        samples_per_pixel: usize,
+        threshold: f32,
+    ) -> Self {
+        let count = columns.len() * rows.len();
+        Self {
+            columns,
+            rows,
+            first_sample: MAX_SHARE * rendered,
+            samples_per_pixel: samples_per_pixel - rendered,
+            threshold,
+            pixels: vec![Default::default(); count],
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sample<I: Integrator + ?Sized>(
+        &mut self,
+        integrator: &I,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        sampler: &mut dyn Sampler,
+        index: usize,
+        samples: usize,
+    ) {
+        let width = self.columns.len();
+        let pixel = (
+            self.columns.start + index % width,
+            self.rows.start + index / width,
+        );
+        for _ in 0..samples {
+            let sample = self.first_sample + self.pixels[index].count;
+            let color = sample_pixel(integrator, scene, camera, film, sampler, pixel, sample);
+            self.pixels[index].add(&color);
+        }
+    }
+
+    pub fn render<I: Integrator + ?Sized>(
+        &mut self,
+        integrator: &I,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        sampler: &mut dyn Sampler,
+        progress: &ProgressBar,
+    ) {
+        let count = self.pixels.len();
+        let mut budget = count * self.samples_per_pixel;
+        let max_samples = MAX_SHARE * self.samples_per_pixel;
+
+        // Every pixel needs a few samples before its variance means anything.
+        let initial = (self.samples_per_pixel / 4)
+            .clamp(2, 16)
+            .min(self.samples_per_pixel);
+        for index in 0..count {
+            self.sample(integrator, scene, camera, film, sampler, index, initial);
+            progress.inc(initial as u64);
+        }
+        budget -= initial * count;
+
+        while budget > 0 {
+            let mut active: Vec<_> = (0..count)
+                .filter(|&index| {
+                    let pixel = &self.pixels[index];
+                    pixel.count < max_samples && pixel.relative_error() >= self.threshold
+                })
+                .collect();
+            if active.is_empty() {
+                // Everything is below the threshold, but the whole budget is still spent, on the
+                // noisiest pixels first.
+                active = (0..count)
+                    .filter(|&index| self.pixels[index].count < max_samples)
+                    .collect();
+                active.sort_by(|&a, &b| {
+                    let error = |index: usize| self.pixels[index].relative_error();
+                    error(b).total_cmp(&error(a))
+                });
+            }
+            if active.is_empty() {
+                break;
+            }
+
+            // Small batches, so that pixels are checked again soon after converging.
+            let batch = (budget / active.len()).clamp(1, initial.max(1));
+            for index in active {
+                let samples = batch
+                    .min(budget)
+                    .min(max_samples - self.pixels[index].count);
+                self.sample(integrator, scene, camera, film, sampler, index, samples);
+                progress.inc(samples as u64);
+                budget -= samples;
+                if budget == 0 {
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,524 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::{record_first_hit, Integrator};
+use crate::camera::Camera;
+use crate::film::{Aov, AovSample, Film};
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::surface::{Hit, Scatter, Surface};
+use crate::{Color, Ray, Vector};
+
+// Bidirectional path tracing (Veach 1997), with every strategy combined by the balance heuristic.
+// Connections only go through materials which can be evaluated, everything else is treated as
+// specular.
+pub struct Bidirectional {
+    max_depth: usize,
+}
+
+impl Bidirectional {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(max_depth: usize) -> Arc<dyn Integrator> {
+        Arc::new(Self { max_depth })
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Kind<'s> {
+    Camera,
+    Light,
+    Surface(Hit<'s>),
+}
+
+#[derive(Copy, Clone)]
+struct Vertex<'s> {
+    kind: Kind<'s>,
+    point: Vector,
+    // For surfaces, this faces the side the path arrived from.
+    normal: Vector,
+    // Towards the previous vertex on the path.
+    previous: Vector,
+    throughput: Vector,
+    delta: bool,
+    // Area densities of sampling this vertex from the previous one, and the other way around.
+    pdf_forward: f32,
+    pdf_reverse: f32,
+}
+
+impl<'s> Vertex<'s> {
+    fn endpoint(kind: Kind<'s>, point: Vector, normal: Vector, throughput: Vector) -> Self {
+        Self {
+            kind,
+            point,
+            normal,
+            previous: Vector::new(0.0, 0.0, 0.0),
+            throughput,
+            delta: false,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+        }
+    }
+
+    fn on_surface(&self) -> bool {
+        !matches!(self.kind, Kind::Camera)
+    }
+
+    fn connectible(&self) -> bool {
+        match self.kind {
+            Kind::Surface(_) => !self.delta,
+            _ => true,
+        }
+    }
+
+    fn emitted(&self) -> Vector {
+        match self.kind {
+            Kind::Surface(ref hit) => *hit.material().emitted(hit),
+            _ => Vector::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn is_light(&self) -> bool {
+        match self.kind {
+            Kind::Light => true,
+            Kind::Surface(_) => !self.emitted().near_zero(),
+            Kind::Camera => false,
+        }
+    }
+
+    fn convert_density(&self, pdf: f32, next: &Vertex) -> f32 {
+        let to_next = next.point - self.point;
+        let distance_squared = to_next.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+
+        let mut ret = pdf / distance_squared;
+        if next.on_surface() {
+            ret *= next.normal.dot(&to_next.unit()).abs();
+        }
+        ret
+    }
+
+    // The BSDF, for light travelling between the previous vertex and `next`.
+    fn f(&self, next: &Vertex) -> Vector {
+        match self.kind {
+            Kind::Surface(ref hit) => hit
+                .material()
+                .evaluate(hit, &self.previous, &(next.point - self.point).unit())
+                .map(|(f, _)| f)
+                .unwrap_or_else(|| Vector::new(0.0, 0.0, 0.0)),
+            _ => Vector::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    // Area density with which this vertex would sample `next`, having been reached from `previous`.
+    fn pdf(&self, camera: &dyn Camera, previous: Option<&Vertex>, next: &Vertex) -> f32 {
+        let direction = match self.kind {
+            Kind::Light => return self.pdf_light(next),
+            Kind::Camera => camera.pdf_direction(&Ray::new(self.point, next.point - self.point)),
+            Kind::Surface(ref hit) => match previous {
+                Some(previous) => hit
+                    .material()
+                    .evaluate(
+                        hit,
+                        &(previous.point - self.point).unit(),
+                        &(next.point - self.point).unit(),
+                    )
+                    .map(|(_, pdf)| pdf)
+                    .unwrap_or(0.0),
+                None => 0.0,
+            },
+        };
+        self.convert_density(direction, next)
+    }
+
+    // Area density with which this vertex, as a light, would emit towards `next`.
+    fn pdf_light(&self, next: &Vertex) -> f32 {
+        let direction = self.normal.dot(&(next.point - self.point).unit()).max(0.0) / PI;
+        self.convert_density(direction, next)
+    }
+
+    // Area density with which this vertex would be chosen as the start of a light path.
+    fn pdf_light_origin(&self, scene: &Scene) -> f32 {
+        scene
+            .light_at(&self.point)
+            .map(|light| 1.0 / (scene.lights().len() as f32 * light.area()))
+            .unwrap_or(0.0)
+    }
+}
+
+fn remap(pdf: f32) -> f32 {
+    if pdf == 0.0 {
+        1.0
+    } else {
+        pdf
+    }
+}
+
+fn geometry(scene: &Scene, a: &Vertex, b: &Vertex) -> f32 {
+    if !scene.visible(&a.point, &b.point) {
+        return 0.0;
+    }
+
+    let direction = b.point - a.point;
+    let distance_squared = direction.length_squared();
+    let direction = direction.unit();
+
+    let mut ret = 1.0 / distance_squared;
+    if a.on_surface() {
+        ret *= a.normal.dot(&direction).abs();
+    }
+    if b.on_surface() {
+        ret *= b.normal.dot(&direction).abs();
+    }
+    ret
+}
+
+impl Bidirectional {
+    // Extends `path` by following `ray` until it is absorbed or `max_vertices` surfaces have been
+    // hit. Returns the throughput-weighted background, if the path escaped the scene.
+    #[allow(clippy::too_many_arguments)]
+    fn walk<'s>(
+        &self,
+        scene: &'s Scene,
+        mut ray: Ray,
+        mut throughput: Vector,
+        mut pdf_forward: f32,
+        max_vertices: usize,
+        path: &mut Vec<Vertex<'s>>,
+        sampler: &mut dyn Sampler,
+    ) -> Option<Vector> {
+        for _ in 0..max_vertices {
+            let hit = match scene.hit(&ray, (1e-3, f32::INFINITY)) {
+                Some(hit) => hit,
+                None => return Some(throughput * scene.background(&ray)),
+            };
+
+            let outgoing = -ray.direction().unit();
+            let mut vertex = Vertex {
+                kind: Kind::Surface(hit),
+                point: *hit.point(),
+                normal: *hit.normal(),
+                previous: outgoing,
+                throughput,
+                delta: false,
+                pdf_forward: 0.0,
+                pdf_reverse: 0.0,
+            };
+            vertex.pdf_forward = path[path.len() - 1].convert_density(pdf_forward, &vertex);
+
+            let scatter = hit
+                .material()
+                .scatter(&ray, &hit, sampler)
+                .filter(|scatter| !hit.leaks(scatter.ray.direction()));
+            let Scatter {
+                ray: scattered,
+                attenuation,
+            } = match scatter {
+                Some(scatter) => scatter,
+                None => {
+                    path.push(vertex);
+                    break;
+                }
+            };
+
+            let incoming = scattered.direction().unit();
+            let material = hit.material();
+            let pdf_reverse = match material.evaluate(&hit, &outgoing, &incoming) {
+                Some((_, pdf)) if pdf > 0.0 => {
+                    pdf_forward = pdf;
+                    material
+                        .evaluate(&hit, &incoming, &outgoing)
+                        .map(|(_, pdf)| pdf)
+                        .unwrap_or(0.0)
+                }
+                _ => {
+                    vertex.delta = true;
+                    pdf_forward = 0.0;
+                    0.0
+                }
+            };
+
+            let previous = path.len() - 1;
+            path[previous].pdf_reverse = vertex.convert_density(pdf_reverse, &path[previous]);
+            path.push(vertex);
+
+            throughput = throughput * *attenuation;
+            ray = scattered;
+        }
+
+        None
+    }
+
+    fn camera_path<'s>(
+        &self,
+        ray: &Ray,
+        scene: &'s Scene,
+        camera: &dyn Camera,
+        escaped: &mut Vector,
+        sampler: &mut dyn Sampler,
+    ) -> Vec<Vertex<'s>> {
+        let one = Vector::new(1.0, 1.0, 1.0);
+        let mut path = Vec::with_capacity(self.max_depth + 2);
+        let mut start = Vertex::endpoint(Kind::Camera, *ray.origin(), camera.forward(), one);
+        // Leaves out the strategies which connect to the camera when it can't be connected to.
+        start.delta = !camera.connectible();
+        path.push(start);
+
+        if let Some(background) = self.walk(
+            scene,
+            *ray,
+            one,
+            camera.pdf_direction(ray),
+            self.max_depth + 1,
+            &mut path,
+            sampler,
+        ) {
+            *escaped = background;
+        }
+        path
+    }
+
+    fn light_path<'s>(&self, scene: &'s Scene, sampler: &mut dyn Sampler) -> Vec<Vertex<'s>> {
+        let mut path = Vec::with_capacity(self.max_depth + 1);
+        let light = match scene.sample_light(sampler) {
+            Some(light) => light,
+            None => return path,
+        };
+
+        let (point, normal) = light.sample_point(sampler);
+        let pdf_position = 1.0 / (scene.lights().len() as f32 * light.area());
+
+        let mut direction = normal + sampler.unit_vector();
+        if direction.near_zero() {
+            direction = normal;
+        }
+        let direction = direction.unit();
+        let pdf_direction = normal.dot(&direction) / PI;
+
+        let mut vertex = Vertex::endpoint(Kind::Light, point, normal, light.radiance());
+        vertex.pdf_forward = pdf_position;
+        path.push(vertex);
+
+        // The cosine cancels against the pdf of the cosine weighted direction.
+        let throughput = light.radiance() * (PI / pdf_position);
+        self.walk(
+            scene,
+            Ray::new(point, direction),
+            throughput,
+            pdf_direction,
+            self.max_depth,
+            &mut path,
+            sampler,
+        );
+        path
+    }
+
+    // Combines the first `s` vertices of the light path with the first `t` of the camera path.
+    // `sampled` replaces the last light or camera vertex when `s` or `t` is 1.
+    #[allow(clippy::too_many_arguments)]
+    fn mis_weight(
+        &self,
+        scene: &Scene,
+        camera: &dyn Camera,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        sampled: Option<Vertex>,
+        s: usize,
+        t: usize,
+    ) -> f32 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        let mut light = light_path[..s].to_vec();
+        let mut eye = camera_path[..t].to_vec();
+        if let Some(sampled) = sampled {
+            if s == 1 {
+                light[0] = sampled;
+            } else if t == 1 {
+                eye[0] = sampled;
+            }
+        }
+
+        // Fill in the reverse densities across the connection.
+        eye[t - 1].delta = false;
+        eye[t - 1].pdf_reverse = if s > 0 {
+            light[s - 1].pdf(camera, s.checked_sub(2).map(|i| &light[i]), &eye[t - 1])
+        } else {
+            eye[t - 1].pdf_light_origin(scene)
+        };
+        if t > 1 {
+            eye[t - 2].pdf_reverse = if s > 0 {
+                eye[t - 1].pdf(camera, Some(&light[s - 1]), &eye[t - 2])
+            } else {
+                eye[t - 1].pdf_light(&eye[t - 2])
+            };
+        }
+        if s > 0 {
+            light[s - 1].delta = false;
+            light[s - 1].pdf_reverse =
+                eye[t - 1].pdf(camera, t.checked_sub(2).map(|i| &eye[i]), &light[s - 1]);
+        }
+        if s > 1 {
+            light[s - 2].pdf_reverse = light[s - 1].pdf(camera, Some(&eye[t - 1]), &light[s - 2]);
+        }
+
+        let mut sum = 0.0;
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            ratio *= remap(eye[i].pdf_reverse) / remap(eye[i].pdf_forward);
+            if !eye[i].delta && !eye[i - 1].delta {
+                sum += ratio;
+            }
+        }
+
+        let mut ratio = 1.0;
+        for i in (0..s).rev() {
+            ratio *= remap(light[i].pdf_reverse) / remap(light[i].pdf_forward);
+            if !light[i].delta && (i == 0 || !light[i - 1].delta) {
+                sum += ratio;
+            }
+        }
+
+        1.0 / (1.0 + sum)
+    }
+
+    // Light carried by the path made of `s` light vertices and `t` camera vertices, along with the
+    // film position it should be splatted to when it doesn't belong to the current pixel.
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        &self,
+        scene: &Scene,
+        camera: &dyn Camera,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Vector, Option<(f32, f32)>)> {
+        let mut sampled = None;
+        let mut uv = None;
+
+        let contribution = if s == 0 {
+            let end = &camera_path[t - 1];
+            if !end.is_light() {
+                return None;
+            }
+            end.emitted() * end.throughput
+        } else if t == 1 {
+            let end = &light_path[s - 1];
+            if !end.connectible() {
+                return None;
+            }
+            let lens = camera.sample_lens(&end.point, sampler)?;
+            let vertex = Vertex::endpoint(
+                Kind::Camera,
+                lens.point,
+                camera.forward(),
+                Vector::new(1.0, 1.0, 1.0) * (lens.importance / lens.pdf),
+            );
+            uv = Some(lens.uv);
+            sampled = Some(vertex);
+
+            let direction = (lens.point - end.point).unit();
+            let contribution = end.throughput
+                * end.f(&vertex)
+                * vertex.throughput
+                * end.normal.dot(&direction).abs();
+            if contribution.near_zero() || !scene.visible(&end.point, &lens.point) {
+                return None;
+            }
+            contribution
+        } else if s == 1 {
+            let end = &camera_path[t - 1];
+            if !end.connectible() {
+                return None;
+            }
+            let light = scene.sample_light(sampler)?;
+            let (point, normal) = light.sample_point(sampler);
+
+            let to_light = point - end.point;
+            let distance_squared = to_light.length_squared();
+            let direction = to_light.unit();
+            let light_cosine = normal.dot(&-direction);
+            if light_cosine <= 0.0 {
+                return None;
+            }
+
+            // Convert the area density of the light point to a solid angle one.
+            let pdf_origin = 1.0 / (scene.lights().len() as f32 * light.area());
+            let pdf = pdf_origin * distance_squared / light_cosine;
+            let mut vertex = Vertex::endpoint(Kind::Light, point, normal, light.radiance() / pdf);
+            vertex.pdf_forward = pdf_origin;
+            sampled = Some(vertex);
+
+            let contribution = end.throughput
+                * end.f(&vertex)
+                * vertex.throughput
+                * end.normal.dot(&direction).abs();
+            if contribution.near_zero() || !scene.visible(&end.point, &point) {
+                return None;
+            }
+            contribution
+        } else {
+            let (light_end, camera_end) = (&light_path[s - 1], &camera_path[t - 1]);
+            if !light_end.connectible() || !camera_end.connectible() {
+                return None;
+            }
+
+            let contribution = light_end.throughput
+                * light_end.f(camera_end)
+                * camera_end.f(light_end)
+                * camera_end.throughput;
+            if contribution.near_zero() {
+                return None;
+            }
+            contribution * geometry(scene, light_end, camera_end)
+        };
+
+        let weight = self.mis_weight(scene, camera, light_path, camera_path, sampled, s, t);
+        Some((contribution * weight, uv))
+    }
+}
+
+impl Integrator for Bidirectional {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut radiance = Vector::new(0.0, 0.0, 0.0);
+        let camera_path = self.camera_path(ray, scene, camera, &mut radiance, sampler);
+        let light_path = self.light_path(scene, sampler);
+
+        match camera_path.get(1) {
+            Some(Vertex {
+                kind: Kind::Surface(hit),
+                ..
+            }) => record_first_hit(aovs, ray, hit, camera),
+            _ => aovs.set(Aov::Emission, radiance),
+        }
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                let depth = s + t;
+                if (s == 1 && t == 1) || depth < 2 || depth - 2 > self.max_depth {
+                    continue;
+                }
+
+                match self.connect(scene, camera, &light_path, &camera_path, s, t, sampler) {
+                    Some((contribution, Some(uv))) => film.splat(uv, &contribution),
+                    Some((contribution, None)) => radiance += &contribution,
+                    None => {}
+                }
+            }
+        }
+
+        radiance.into()
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use super::{record_first_hit, Integrator};
+use crate::camera::Camera;
+use crate::film::{AovSample, Film};
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::surface::{self, Surface};
+use crate::{Color, Ray, Vector};
+
+// Fraction of the hemisphere above the first hit which is open within `radius`.
+pub struct AmbientOcclusion {
+    radius: f32,
+}
+
+impl AmbientOcclusion {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(radius: f32) -> Arc<dyn Integrator> {
+        Arc::new(Self { radius })
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        _film: &mut Film,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let hit = match scene.hit(ray, (1e-3, f32::INFINITY)) {
+            Some(hit) => hit,
+            None => return Color::new(1.0, 1.0, 1.0),
+        };
+        record_first_hit(aovs, ray, &hit, camera);
+
+        let mut direction = *hit.geometric_normal() + sampler.unit_vector();
+        if direction.near_zero() {
+            direction = *hit.geometric_normal();
+        }
+        let direction = direction.unit();
+        match scene.hit(&Ray::new(*hit.point(), direction), (1e-3, self.radius)) {
+            Some(_) => Color::new(0.0, 0.0, 0.0),
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum DebugView {
+    ShadingNormal,
+    GeometricNormal,
+    Depth,
+    Uv,
+    MaterialId,
+    PrimitiveId,
+    // Bounding boxes and primitives tested to find the first hit.
+    TraversalCost,
+}
+
+// Shows one property of the first hit along each camera ray.
+pub struct Debug {
+    view: DebugView,
+}
+
+impl Debug {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(view: DebugView) -> Arc<dyn Integrator> {
+        Arc::new(Self { view })
+    }
+}
+
+// Tests at which the heatmap saturates.
+const MAX_TRAVERSAL_COST: f32 = 1024.0;
+
+fn id_color<T: Hash>(id: T) -> Color {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let hash = hasher.finish();
+    Color::new(
+        (hash & 0xff) as f32 / 255.0,
+        (hash >> 8 & 0xff) as f32 / 255.0,
+        (hash >> 16 & 0xff) as f32 / 255.0,
+    )
+}
+
+fn normal_color(normal: &Vector) -> Color {
+    ((*normal + 1.0) / 2.0).into()
+}
+
+impl Integrator for Debug {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        _film: &mut Film,
+        _aovs: &mut AovSample,
+        _sampler: &mut dyn Sampler,
+    ) -> Color {
+        surface::take_traversal_cost();
+        let hit = scene.hit(ray, (1e-3, f32::INFINITY));
+        if let DebugView::TraversalCost = self.view {
+            let cost = surface::take_traversal_cost() as f32;
+            return Color::heatmap((1.0 + cost).ln() / (1.0 + MAX_TRAVERSAL_COST).ln());
+        }
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return Color::new(0.0, 0.0, 0.0),
+        };
+        match self.view {
+            DebugView::ShadingNormal => normal_color(hit.normal()),
+            DebugView::GeometricNormal => normal_color(hit.geometric_normal()),
+            DebugView::Depth => {
+                // Brightest up close, black at twice the focus distance.
+                let depth = camera.depth(ray, hit.point());
+                let value = (1.0 - depth / (2.0 * camera.focus_distance())).clamp(0.0, 1.0);
+                Color::new(value, value, value)
+            }
+            DebugView::Uv => {
+                let (u, v) = hit.uv();
+                Color::new(u, v, 0.0)
+            }
+            DebugView::MaterialId => id_color(scene.material_id(hit.material())),
+            DebugView::PrimitiveId => id_color(hit.primitive()),
+            DebugView::TraversalCost => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+mod adaptive;
+mod bdpt;
+mod debug;
+mod path;
+mod sppm;
+
+pub use bdpt::Bidirectional;
+pub use debug::{AmbientOcclusion, Debug, DebugView};
+pub use path::PathTracer;
+pub use sppm::PhotonMapper;
+
+use std::ops::Range;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use indicatif::ProgressBar;
+
+use crate::camera::Camera;
+use crate::film::{Aov, AovSample, Film};
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::surface::Hit;
+use crate::{Color, Ray, Vector};
+
+// Rows of pixels handed to a thread at a time. Fixed, so that adaptive sampling, which shares its
+// budget between the pixels of a block, doesn't depend on the number of threads.
+const BLOCK_ROWS: usize = 8;
+
+pub struct RenderOptions<'a> {
+    pub samples_per_pixel: usize,
+    // Sample adaptively, stopping pixels once their relative error is below this.
+    pub noise_threshold: Option<f32>,
+    pub jobs: usize,
+    pub sampler: &'a (dyn Fn() -> Box<dyn Sampler> + Sync),
+    pub checkpoint: Option<Checkpoint<'a>>,
+    // Stops taking on more work after this, leaving the film part way rendered.
+    pub deadline: Option<Instant>,
+}
+
+// Saves the film every `interval` while rendering, so that the render can be carried on with if
+// it's stopped.
+pub struct Checkpoint<'a> {
+    pub interval: Duration,
+    pub save: &'a (dyn Fn(&Film) + Sync),
+}
+
+pub trait Integrator: Send + Sync {
+    // Light arriving along a camera ray. Integrators which also carry light to other pixels can
+    // splat it onto `film`, and fill in whichever `aovs` they can.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> Color;
+
+    // Brings every pixel of `film` up to `samples_per_pixel` samples, or as many in total spread
+    // according to noise when there is a `noise_threshold`. Every pixel is sampled on a single
+    // thread, so the result doesn't depend on how many there are, nor on whether the render was
+    // resumed from a checkpoint. Integrators which share work between pixels override this.
+    fn render(
+        &self,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        options: &RenderOptions,
+        progress: &ProgressBar,
+    ) {
+        let window = film.sample_window();
+        let height = window.size().1;
+        let blocks: Vec<_> = (0..height.div_ceil(BLOCK_ROWS))
+            .map(|block| {
+                // Top to bottom.
+                let end = window.max.1 - block * BLOCK_ROWS;
+                let rows = end.saturating_sub(BLOCK_ROWS).max(window.min.1)..end;
+                // Blocks are rendered whole, so their rows have all been rendered alike.
+                let rendered = film.rendered(rows.start);
+                (rows, rendered)
+            })
+            .filter(|(_, rendered)| *rendered < options.samples_per_pixel)
+            .collect();
+        let blocks = Mutex::new(blocks.into_iter());
+
+        // Threads stop taking blocks when a checkpoint is due, so that it holds whole blocks.
+        loop {
+            let due = options
+                .checkpoint
+                .as_ref()
+                .map(|checkpoint| Instant::now() + checkpoint.interval)
+                .into_iter()
+                .chain(options.deadline)
+                .min();
+            let films = in_parallel(options, |sampler| {
+                let mut film = film.cleared();
+                loop {
+                    let (rows, rendered) = match blocks.lock().unwrap().next() {
+                        Some(block) => block,
+                        None => break,
+                    };
+                    render_rows(
+                        self,
+                        scene,
+                        camera,
+                        &mut film,
+                        rows.clone(),
+                        rendered,
+                        options,
+                        sampler,
+                        progress,
+                    );
+                    film.set_rendered(rows, options.samples_per_pixel);
+                    if due.is_some_and(|due| Instant::now() >= due) {
+                        break;
+                    }
+                }
+                film
+            });
+            for other in &films {
+                film.merge(other);
+            }
+
+            if blocks.lock().unwrap().len() == 0
+                || options
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                break;
+            }
+            if let Some(checkpoint) = &options.checkpoint {
+                (checkpoint.save)(film);
+            }
+        }
+    }
+}
+
+// Runs `work` on each of `options.jobs` threads with a sampler of its own, and returns what each
+// returned.
+fn in_parallel<T: Send>(
+    options: &RenderOptions,
+    work: impl Fn(&mut dyn Sampler) -> T + Sync,
+) -> Vec<T> {
+    let work = &work;
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..options.jobs.max(1))
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut sampler = (options.sampler)();
+                    work(sampler.as_mut())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render_rows<I: Integrator + ?Sized>(
+    integrator: &I,
+    scene: &Scene,
+    camera: &dyn Camera,
+    film: &mut Film,
+    rows: Range<usize>,
+    rendered: usize,
+    options: &RenderOptions,
+    sampler: &mut dyn Sampler,
+    progress: &ProgressBar,
+) {
+    let samples_per_pixel = options.samples_per_pixel;
+    let window = film.sample_window();
+    let columns = window.min.0..window.max.0;
+    if let Some(threshold) = options.noise_threshold {
+        let mut scheduler =
+            adaptive::Scheduler::new(columns, rows, rendered, samples_per_pixel, threshold);
+        scheduler.render(integrator, scene, camera, film, sampler, progress);
+        return;
+    }
+
+    for j in rows.rev() {
+        for i in columns.clone() {
+            for index in rendered..samples_per_pixel {
+                sample_pixel(integrator, scene, camera, film, sampler, (i, j), index);
+            }
+            progress.inc((samples_per_pixel - rendered) as u64);
+        }
+    }
+}
+
+// Traces camera ray number `index` through `pixel` and records it on the film.
+fn sample_pixel<I: Integrator + ?Sized>(
+    integrator: &I,
+    scene: &Scene,
+    camera: &dyn Camera,
+    film: &mut Film,
+    sampler: &mut dyn Sampler,
+    pixel: (usize, usize),
+    index: usize,
+) -> Color {
+    sampler.start_pixel_sample(pixel, index);
+    let dimensions = film.dimensions();
+    let (offset, weight) = film.filter().sample(sampler.next_2d());
+    let u = (pixel.0 as f32 + 0.5 + offset.0) / dimensions.0 as f32;
+    let v = (pixel.1 as f32 + 0.5 + offset.1) / dimensions.1 as f32;
+
+    let mut aovs = AovSample::default();
+    let color = match camera.ray_from((u, v), sampler) {
+        Some(sample) => integrator
+            .radiance(&sample.ray, scene, camera, film, &mut aovs, sampler)
+            .weighted(&sample.weight),
+        None => Color::new(0.0, 0.0, 0.0),
+    };
+    film.add_sample(pixel, offset, weight, &color);
+    film.add_aovs(pixel, &aovs);
+    color
+}
+
+// Whether a surface scatters light diffusely, rather than through a specular lobe.
+fn diffuse(hit: &Hit) -> bool {
+    hit.material()
+        .evaluate(hit, hit.normal(), hit.normal())
+        .is_some()
+}
+
+// Fills in the AOVs which only depend on the first surface a camera ray hits.
+fn record_first_hit(aovs: &mut AovSample, ray: &Ray, hit: &Hit, camera: &dyn Camera) {
+    let depth = camera.depth(ray, hit.point());
+    aovs.set(Aov::Albedo, *hit.material().albedo(hit));
+    aovs.set(Aov::Normal, *hit.normal());
+    aovs.set(Aov::Depth, Vector::new(depth, depth, depth));
+    aovs.set(Aov::Position, *hit.point());
+    aovs.set(Aov::Emission, *hit.material().emitted(hit));
+}
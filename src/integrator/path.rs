@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use super::{diffuse, record_first_hit, Integrator};
+use crate::camera::Camera;
+use crate::film::{Aov, AovSample, Film};
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::surface::{Scatter, Surface};
+use crate::{Color, Ray, Vector};
+
+pub struct PathTracer {
+    max_depth: usize,
+    // Paths at least this long are subject to Russian roulette.
+    roulette_depth: usize,
+}
+
+impl PathTracer {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(max_depth: usize, roulette_depth: usize) -> Arc<dyn Integrator> {
+        Arc::new(Self {
+            max_depth,
+            roulette_depth,
+        })
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        _film: &mut Film,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut ray = *ray;
+        let mut throughput = Vector::new(1.0, 1.0, 1.0);
+        let mut radiance = Vector::new(0.0, 0.0, 0.0);
+        // Where light arriving after each bounce goes, decided by the first one.
+        let mut lobe = (Aov::Emission, Aov::Emission);
+
+        for depth in 0..self.max_depth {
+            let aov = match depth {
+                0 => Aov::Emission,
+                1 => lobe.0,
+                _ => lobe.1,
+            };
+            let hit = match scene.hit(&ray, (1e-3, f32::INFINITY)) {
+                Some(hit) => hit,
+                None => {
+                    let light = throughput * scene.background(&ray);
+                    aovs.add(aov, &light);
+                    radiance += &light;
+                    break;
+                }
+            };
+            let light = throughput * *hit.material().emitted(&hit);
+            radiance += &light;
+            if depth == 0 {
+                record_first_hit(aovs, &ray, &hit, camera);
+                lobe = if diffuse(&hit) {
+                    (Aov::DiffuseDirect, Aov::DiffuseIndirect)
+                } else {
+                    (Aov::SpecularDirect, Aov::SpecularIndirect)
+                };
+            } else {
+                aovs.add(aov, &light);
+            }
+
+            let Scatter {
+                ray: scattered,
+                attenuation,
+            } = match hit
+                .material()
+                .scatter(&ray, &hit, sampler)
+                .filter(|scatter| !hit.leaks(scatter.ray.direction()))
+            {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            throughput = throughput * *attenuation;
+            if depth >= self.roulette_depth {
+                let survival = throughput.max_component().clamp(0.0, 1.0);
+                if sampler.next_1d() >= survival {
+                    break;
+                }
+                throughput = throughput / survival;
+            }
+
+            ray = scattered;
+        }
+
+        radiance.into()
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use indicatif::ProgressBar;
+
+use super::{diffuse, in_parallel, record_first_hit, Integrator, RenderOptions};
+use crate::camera::{Camera, CameraRay};
+use crate::film::{Aov, AovSample, ExactSum, Film};
+use crate::sampler::Sampler;
+use crate::scene::Scene;
+use crate::surface::{Hit, Scatter, Surface};
+use crate::{Color, Ray, Vector};
+
+// Stochastic progressive photon mapping (Hachisuka and Jensen 2009). Every pass traces one camera
+// path per pixel to its first diffuse surface, then gathers photons from the lights around it,
+// shrinking the gather radius as photons accumulate. Light which doesn't come from
+// `Scene::lights` only reaches the camera directly or through specular surfaces.
+pub struct PhotonMapper {
+    max_depth: usize,
+    photons_per_pass: usize,
+    initial_radius: f32,
+}
+
+// Fraction of each pass's photons kept, which trades bias for noise.
+const ALPHA: f32 = 2.0 / 3.0;
+// Pixels and photons handed to a thread at a time.
+const BATCH: usize = 1024;
+
+impl PhotonMapper {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        max_depth: usize,
+        photons_per_pass: usize,
+        initial_radius: f32,
+    ) -> Arc<dyn Integrator> {
+        Arc::new(Self {
+            max_depth,
+            photons_per_pass,
+            initial_radius,
+        })
+    }
+}
+
+#[derive(Copy, Clone)]
+struct VisiblePoint<'s> {
+    hit: Hit<'s>,
+    outgoing: Vector,
+    throughput: Vector,
+    // Whether no specular surface was passed through on the way.
+    first_hit: bool,
+}
+
+struct Pixel<'s> {
+    radius: f32,
+    // Photons gathered over all passes so far, and the flux they carried.
+    photons: f32,
+    flux: Vector,
+    direct: Vector,
+    aovs: AovSample,
+    // Passes in which the visible point was seen directly, rather than through specular surfaces.
+    diffuse_passes: usize,
+    visible: Option<VisiblePoint<'s>>,
+}
+
+// Photons gathered by a pixel during the current pass. The flux is summed exactly, so that it
+// doesn't matter which thread traced which photon.
+#[derive(Copy, Clone, Default)]
+struct Gathered {
+    photons: usize,
+    flux: ExactSum,
+}
+
+// Spatial hash from grid cells to the pixels whose gather sphere overlaps them.
+struct Grid {
+    cell_size: f32,
+    cells: HashMap<[i32; 3], Vec<usize>>,
+}
+
+impl Grid {
+    fn new(pixels: &[Pixel]) -> Self {
+        let cell_size = pixels
+            .iter()
+            .filter(|pixel| pixel.visible.is_some())
+            .fold(0.0, |acc: f32, pixel| acc.max(pixel.radius));
+        let mut ret = Self {
+            cell_size,
+            cells: HashMap::new(),
+        };
+
+        for (i, pixel) in pixels.iter().enumerate() {
+            if let Some(visible) = &pixel.visible {
+                let offset = Vector::new(pixel.radius, pixel.radius, pixel.radius);
+                let low = ret.cell(&(*visible.hit.point() - offset));
+                let high = ret.cell(&(*visible.hit.point() + offset));
+                for x in low[0]..=high[0] {
+                    for y in low[1]..=high[1] {
+                        for z in low[2]..=high[2] {
+                            ret.cells.entry([x, y, z]).or_default().push(i);
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    fn cell(&self, point: &Vector) -> [i32; 3] {
+        [
+            (point[0] / self.cell_size).floor() as i32,
+            (point[1] / self.cell_size).floor() as i32,
+            (point[2] / self.cell_size).floor() as i32,
+        ]
+    }
+
+    fn near(&self, point: &Vector) -> &[usize] {
+        self.cells
+            .get(&self.cell(point))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+}
+
+impl PhotonMapper {
+    // Follows a camera ray through specular surfaces, returning the light picked up on the way and
+    // the first diffuse surface it reaches.
+    fn visible_point<'s>(
+        &self,
+        sample: &CameraRay,
+        scene: &'s Scene,
+        camera: &dyn Camera,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> (Vector, Option<VisiblePoint<'s>>) {
+        let camera_ray = sample.ray;
+        let mut ray = sample.ray;
+        let mut throughput = sample.weight;
+        let mut radiance = Vector::new(0.0, 0.0, 0.0);
+
+        for depth in 0..self.max_depth {
+            let aov = match depth {
+                0 => Aov::Emission,
+                1 => Aov::SpecularDirect,
+                _ => Aov::SpecularIndirect,
+            };
+            let hit = match scene.hit(&ray, (1e-3, f32::INFINITY)) {
+                Some(hit) => hit,
+                None => {
+                    let light = throughput * scene.background(&ray);
+                    aovs.add(aov, &light);
+                    radiance += &light;
+                    break;
+                }
+            };
+            let light = throughput * *hit.material().emitted(&hit);
+            radiance += &light;
+            if depth == 0 {
+                record_first_hit(aovs, &camera_ray, &hit, camera);
+            } else {
+                aovs.add(aov, &light);
+            }
+
+            let outgoing = -ray.direction().unit();
+            if diffuse(&hit) {
+                let light = throughput * self.direct(scene, &hit, &outgoing, sampler);
+                let aov = match depth {
+                    0 => Aov::DiffuseDirect,
+                    _ => Aov::SpecularIndirect,
+                };
+                aovs.add(aov, &light);
+                radiance += &light;
+                return (
+                    radiance,
+                    Some(VisiblePoint {
+                        hit,
+                        outgoing,
+                        throughput,
+                        first_hit: depth == 0,
+                    }),
+                );
+            }
+
+            let Scatter {
+                ray: scattered,
+                attenuation,
+            } = match hit
+                .material()
+                .scatter(&ray, &hit, sampler)
+                .filter(|scatter| !hit.leaks(scatter.ray.direction()))
+            {
+                Some(scatter) => scatter,
+                None => break,
+            };
+            throughput = throughput * *attenuation;
+            ray = scattered;
+        }
+
+        (radiance, None)
+    }
+
+    // Light arriving straight from a light source, which photons leave out.
+    fn direct(
+        &self,
+        scene: &Scene,
+        hit: &Hit,
+        outgoing: &Vector,
+        sampler: &mut dyn Sampler,
+    ) -> Vector {
+        let none = Vector::new(0.0, 0.0, 0.0);
+        let light = match scene.sample_light(sampler) {
+            Some(light) => light,
+            None => return none,
+        };
+        let (point, normal) = light.sample_point(sampler);
+
+        let to_light = point - *hit.point();
+        let direction = to_light.unit();
+        let light_cosine = normal.dot(&-direction);
+        if light_cosine <= 0.0 || !scene.visible(hit.point(), &point) {
+            return none;
+        }
+
+        let f = match hit.material().evaluate(hit, outgoing, &direction) {
+            Some((f, _)) => f,
+            None => return none,
+        };
+        let pdf =
+            to_light.length_squared() / (light_cosine * light.area()) / scene.lights().len() as f32;
+        f * light.radiance() * (hit.normal().dot(&direction).abs() / pdf)
+    }
+
+    fn trace_photon(
+        &self,
+        scene: &Scene,
+        grid: &Grid,
+        pixels: &[Pixel],
+        gathered: &mut [Gathered],
+        sampler: &mut dyn Sampler,
+    ) {
+        let light = match scene.sample_light(sampler) {
+            Some(light) => light,
+            None => return,
+        };
+        let (point, normal) = light.sample_point(sampler);
+        let mut direction = normal + sampler.unit_vector();
+        if direction.near_zero() {
+            direction = normal;
+        }
+
+        // Cosine weighted emission, so only the area and choice of light remain in the weight.
+        let mut flux = light.radiance() * (PI * light.area() * scene.lights().len() as f32);
+        let mut ray = Ray::new(point, direction);
+
+        for depth in 0..self.max_depth {
+            let hit = match scene.hit(&ray, (1e-3, f32::INFINITY)) {
+                Some(hit) => hit,
+                None => break,
+            };
+
+            // Photons arriving straight from the light are already accounted for by `direct`.
+            if depth > 0 && diffuse(&hit) {
+                let incoming = -ray.direction().unit();
+                for &i in grid.near(hit.point()) {
+                    let pixel = &pixels[i];
+                    let visible = match &pixel.visible {
+                        Some(visible) => visible,
+                        None => continue,
+                    };
+                    if (*visible.hit.point() - *hit.point()).length_squared()
+                        > pixel.radius * pixel.radius
+                    {
+                        continue;
+                    }
+                    if let Some((f, _)) =
+                        visible
+                            .hit
+                            .material()
+                            .evaluate(&visible.hit, &visible.outgoing, &incoming)
+                    {
+                        gathered[i].flux.add(&(f * flux));
+                        gathered[i].photons += 1;
+                    }
+                }
+            }
+
+            let Scatter {
+                ray: scattered,
+                attenuation,
+            } = match hit
+                .material()
+                .scatter(&ray, &hit, sampler)
+                .filter(|scatter| !hit.leaks(scatter.ray.direction()))
+            {
+                Some(scatter) => scatter,
+                None => break,
+            };
+
+            let next = flux * *attenuation;
+            let survival = (next.max_component() / flux.max_component()).clamp(0.0, 1.0);
+            if sampler.next_1d() >= survival {
+                break;
+            }
+            flux = next / survival;
+            ray = scattered;
+        }
+    }
+}
+
+impl Integrator for PhotonMapper {
+    // On its own, this only includes light which doesn't need photons.
+    fn radiance(
+        &self,
+        ray: &Ray,
+        scene: &Scene,
+        camera: &dyn Camera,
+        _film: &mut Film,
+        aovs: &mut AovSample,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        self.visible_point(&CameraRay::from(*ray), scene, camera, aovs, sampler)
+            .0
+            .into()
+    }
+
+    fn render(
+        &self,
+        scene: &Scene,
+        camera: &dyn Camera,
+        film: &mut Film,
+        options: &RenderOptions,
+        progress: &ProgressBar,
+    ) {
+        let mut passes = options.samples_per_pixel;
+        if passes == 0 {
+            return;
+        }
+
+        let dimensions = film.dimensions();
+        let window = film.sample_window();
+        let (width, height) = window.size();
+        let position = |index: usize| (window.min.0 + index % width, window.min.1 + index / width);
+        let mut pixels: Vec<_> = (0..width * height)
+            .map(|_| Pixel {
+                radius: self.initial_radius,
+                photons: 0.0,
+                flux: Vector::new(0.0, 0.0, 0.0),
+                direct: Vector::new(0.0, 0.0, 0.0),
+                aovs: AovSample::default(),
+                diffuse_passes: 0,
+                visible: None,
+            })
+            .collect();
+
+        for pass in 0..passes {
+            let batches = Mutex::new(pixels.chunks_mut(BATCH).enumerate());
+            in_parallel(options, |sampler| loop {
+                let (batch, chunk) = match batches.lock().unwrap().next() {
+                    Some(next) => next,
+                    None => break,
+                };
+                for (offset, pixel) in chunk.iter_mut().enumerate() {
+                    let index = batch * BATCH + offset;
+                    let (i, j) = position(index);
+                    sampler.start_pixel_sample((i, j), pass);
+                    let jitter = sampler.next_2d();
+                    let u = (i as f32 + jitter.0) / dimensions.0 as f32;
+                    let v = (j as f32 + jitter.1) / dimensions.1 as f32;
+                    let mut aovs = AovSample::default();
+                    let (direct, visible) = match camera.ray_from((u, v), sampler) {
+                        Some(sample) => {
+                            self.visible_point(&sample, scene, camera, &mut aovs, sampler)
+                        }
+                        None => (Vector::new(0.0, 0.0, 0.0), None),
+                    };
+                    pixel.direct += &direct;
+                    pixel.aovs.merge(&aovs);
+                    if visible.is_some_and(|visible| visible.first_hit) {
+                        pixel.diffuse_passes += 1;
+                    }
+                    pixel.visible = visible;
+                }
+            });
+
+            let grid = Grid::new(&pixels);
+            let mut gathered = vec![Gathered::default(); pixels.len()];
+            if grid.cell_size > 0.0 {
+                let batches = Mutex::new((0..self.photons_per_pass).step_by(BATCH));
+                let parts = in_parallel(options, |sampler| {
+                    let mut gathered = vec![Gathered::default(); pixels.len()];
+                    loop {
+                        let start = match batches.lock().unwrap().next() {
+                            Some(start) => start,
+                            None => break,
+                        };
+                        for photon in start..(start + BATCH).min(self.photons_per_pass) {
+                            // Each photon gets its own sample sequence, as if it were a pixel in
+                            // a row below the image.
+                            sampler.start_pixel_sample((photon, dimensions.1), pass);
+                            self.trace_photon(scene, &grid, &pixels, &mut gathered, sampler);
+                        }
+                    }
+                    gathered
+                });
+                for part in parts {
+                    for (total, part) in gathered.iter_mut().zip(part) {
+                        total.photons += part.photons;
+                        total.flux.merge(&part.flux);
+                    }
+                }
+            }
+
+            for (pixel, gathered) in pixels.iter_mut().zip(&gathered) {
+                if let Some(visible) = &pixel.visible {
+                    if gathered.photons > 0 {
+                        let photons = pixel.photons + ALPHA * gathered.photons as f32;
+                        let shrink = photons / (pixel.photons + gathered.photons as f32);
+                        pixel.flux =
+                            (pixel.flux + visible.throughput * gathered.flux.value()) * shrink;
+                        pixel.radius *= shrink.sqrt();
+                        pixel.photons = photons;
+                    }
+                }
+            }
+            progress.inc(pixels.len() as u64);
+
+            if options
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                passes = pass + 1;
+                break;
+            }
+        }
+
+        let photons = (passes * self.photons_per_pass) as f32;
+        for (index, pixel) in pixels.iter().enumerate() {
+            let indirect = pixel.flux / (photons * PI * pixel.radius * pixel.radius);
+            let color: Color = (pixel.direct / passes as f32 + indirect).into();
+            let pixel_position = position(index);
+            // The estimate covers the whole pixel, so it is filtered as a single sample at its
+            // centre.
+            film.add_sample(pixel_position, (0.0, 0.0), 1.0, &color);
+
+            let mut aovs = pixel.aovs.scaled(1.0 / passes as f32);
+            let diffuse_fraction = pixel.diffuse_passes as f32 / passes as f32;
+            aovs.add(Aov::DiffuseIndirect, &(indirect * diffuse_fraction));
+            aovs.add(
+                Aov::SpecularIndirect,
+                &(indirect * (1.0 - diffuse_fraction)),
+            );
+            film.add_aovs(pixel_position, &aovs);
+        }
+    }
+}
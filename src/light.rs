@@ -0,0 +1,44 @@
+use std::f32::consts::PI;
+
+use crate::sampler::Sampler;
+use crate::surface::{material, Sphere, Surface};
+use crate::{Color, Vector};
+
+// A spherical area light. The geometry is a separate surface with a `DiffuseLight` material, this
+// is how integrators sample it directly.
+pub struct Light {
+    center: Vector,
+    radius: f32,
+    radiance: Vector,
+}
+
+impl Light {
+    pub fn sphere(center: Vector, radius: f32, radiance: Color) -> (Self, Box<dyn Surface>) {
+        (
+            Self {
+                center,
+                radius,
+                radiance: *radiance,
+            },
+            Sphere::new(center, radius, &material::DiffuseLight::new(radiance)),
+        )
+    }
+
+    pub fn area(&self) -> f32 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    pub fn radiance(&self) -> Vector {
+        self.radiance
+    }
+
+    pub fn contains(&self, point: &Vector) -> bool {
+        ((*point - self.center).length() - self.radius).abs() < 1e-3 * self.radius.max(1.0)
+    }
+
+    // A uniformly distributed point on the surface, and the outward normal there.
+    pub fn sample_point(&self, sampler: &mut dyn Sampler) -> (Vector, Vector) {
+        let normal = sampler.unit_vector();
+        (self.center + normal * self.radius, normal)
+    }
+}
@@ -1,89 +1,47 @@
+mod animation;
 mod camera;
 mod color;
+mod denoise;
+mod film;
+mod filter;
+mod integrator;
+mod light;
+mod ppm;
 mod ray;
+mod sampler;
 mod scene;
 mod surface;
+mod texture;
 mod vector;
 
-use std::io::{self, BufWriter, Write};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use std::thread;
+use std::time::{Duration, Instant};
 
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use once_cell::sync::OnceCell;
-use rand::distributions::{Distribution, Uniform};
+use indicatif::{ProgressBar, ProgressStyle};
 use structopt::StructOpt;
 
-use camera::Camera;
+use animation::{Animation, Frames, Interpolation, Keyframe};
+use camera::{
+    Aperture, Camera, CubeMap, Equirectangular, Fisheye, Layout, Lens, LensSystem, Mapping,
+    Orthographic, Perspective, Physical, Realistic, Stereo, View,
+};
 use color::Color;
+use film::{Aov, Film, Window};
+use filter::Filter;
+use integrator::{
+    AmbientOcclusion, Bidirectional, Checkpoint, Debug, DebugView, Integrator, PathTracer,
+    PhotonMapper, RenderOptions,
+};
 use ray::Ray;
+use sampler::{BlueNoise, Halton, Independent, Sampler, Sobol, Stratified};
 use scene::Scene;
-use surface::{Scatter, Surface};
 use vector::Vector;
 
-fn random_unit() -> f32 {
-    static DIST: OnceCell<Uniform<f32>> = OnceCell::new();
-    DIST.get_or_init(|| Uniform::new(0.0, 1.0))
-        .sample(&mut rand::thread_rng())
-}
-
-fn ray_color(ray: &Ray, world: &dyn Surface, depth: usize) -> Color {
-    if depth == 0 {
-        Color::new(0.0, 0.0, 0.0)
-    } else if let Some(hit) = world.hit(ray, (1e-3, f32::INFINITY)) {
-        if let Some(Scatter {
-            ray: ref scattered,
-            attenuation,
-        }) = hit.material().scatter(ray, &hit)
-        {
-            (*ray_color(scattered, world, depth - 1) * *attenuation).into()
-        } else {
-            Color::new(0.0, 0.0, 0.0)
-        }
-    } else {
-        let direction = ray.direction().unit();
-        let t = (direction[1] + 1.0) / 2.0;
-
-        (Vector::new(1.0, 1.0, 1.0) * (1.0 - t) + Vector::new(0.5, 0.7, 1.0) * t).into()
-    }
-}
-
-const MAX_REFLECTION_DEPTH: usize = 50;
-
-fn render(
-    camera: Arc<Camera>,
-    dimensions: (usize, usize),
-    world: Arc<dyn Surface>,
-    samples_per_pixel: usize,
-    progress: ProgressBar,
-) -> Vec<Color> {
-    let mut ret = Vec::with_capacity(dimensions.0 * dimensions.1);
-    let mut rng = rand::thread_rng();
-    let dist = Uniform::new(0.0, 1.0);
-
-    for j in (0..dimensions.1).rev() {
-        for i in 0..dimensions.0 {
-            let mut color = Color::new(0.0, 0.0, 0.0);
-            for _ in 0..samples_per_pixel {
-                let u = (i as f32 + dist.sample(&mut rng)) / (dimensions.0 as f32 - 1.0);
-                let v = (j as f32 + dist.sample(&mut rng)) / (dimensions.1 as f32 - 1.0);
-                color.add_samples(&ray_color(
-                    &camera.ray_from((u, v)),
-                    world.as_ref(),
-                    MAX_REFLECTION_DEPTH,
-                ));
-            }
-
-            ret.push(color);
-            progress.inc(samples_per_pixel as u64);
-        }
-    }
-
-    progress.finish_with_message("Done.");
-    ret
-}
-
 #[derive(StructOpt)]
 #[structopt(setting = structopt::clap::AppSettings::DeriveDisplayOrder, rename_all = "kebab-case")]
 struct Config {
@@ -100,54 +58,497 @@ struct Config {
         short = "f",
         long,
         default_value = "20.0",
-        help = "Vertical field of view, in degrees"
+        help = "Vertical field of view in degrees, or across the image circle for fisheye projections"
     )]
     camera_fov: f32,
     #[structopt(short = "a", long, default_value = "0.1")]
     camera_aperture: f32,
     #[structopt(long)]
     camera_focus_distance: Option<f32>,
+    #[structopt(
+        long,
+        help = "In mm. Sets up a physical camera, whose field of view, depth of field and exposure follow from its settings, in place of --camera-fov and --camera-aperture. Scene units are taken to be metres"
+    )]
+    focal_length: Option<f32>,
+    #[structopt(long, default_value = "36", help = "In mm, for a physical camera")]
+    sensor_width: f32,
+    #[structopt(long, default_value = "16", help = "For a physical camera")]
+    f_number: f32,
+    #[structopt(
+        long,
+        default_value = "0.01",
+        help = "In seconds, for a physical camera"
+    )]
+    shutter_time: f32,
+    #[structopt(long, default_value = "100", help = "For a physical camera")]
+    iso: f32,
+    #[structopt(
+        long,
+        help = "Gives the aperture straight sides, so that defocused highlights are polygons"
+    )]
+    aperture_blades: Option<usize>,
+    #[structopt(long, default_value = "0", help = "In degrees")]
+    aperture_rotation: f32,
+    #[structopt(
+        long,
+        help = "A PPM image of the aperture, letting light through in proportion to its brightness"
+    )]
+    aperture_mask: Option<PathBuf>,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "How much the lens barrel clips the aperture towards the edges of the image, from 0 to about 1"
+    )]
+    cat_eye: f32,
+    #[structopt(
+        long,
+        require_delimiter = true,
+        help = "Moves the image by \"x,y\" fractions of its size, keeping the film parallel to the view"
+    )]
+    lens_shift: Vec<f32>,
+    #[structopt(
+        long,
+        require_delimiter = true,
+        help = "Tilts the plane of focus about the horizontal and vertical axes, by \"x,y\" degrees"
+    )]
+    lens_tilt: Vec<f32>,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Relative difference in focal length between the red, green and blue channels"
+    )]
+    chromatic_aberration: f32,
+    #[structopt(
+        long,
+        help = "A lens prescription to trace rays through, in place of a thin lens. Each line gives a surface's radius, thickness, index of refraction and aperture in mm, from the front. The film is as wide as --sensor-width"
+    )]
+    lens_file: Option<PathBuf>,
+    #[structopt(
+        long,
+        default_value = "perspective",
+        help = "One of \"perspective\", \"orthographic\", \"fisheye-equidistant\", \"fisheye-equisolid\", \"equirectangular\", \"cube-map\". Panoramas should be 2:1 and cube maps 6:1"
+    )]
+    projection: ProjectionName,
+    #[structopt(
+        long,
+        help = "Render both eyes, as \"side-by-side\" or \"top-bottom\". Equirectangular and cube map projections give omni-directional stereo"
+    )]
+    stereo: Option<Layout>,
+    #[structopt(
+        long,
+        default_value = "0.065",
+        help = "Distance between the eyes, for stereo"
+    )]
+    interocular_distance: f32,
+    #[structopt(
+        long,
+        help = "Distance at which the eyes' views line up, for stereo. Defaults to the focus distance, and \"inf\" gives parallel views"
+    )]
+    convergence_distance: Option<f32>,
+    #[structopt(
+        long,
+        help = "Animates the camera. Each line gives a time in seconds, position, target, field of view and, optionally, focus distance, as in \"1.5 13,2,3 0,0,0 20\""
+    )]
+    keyframes: Option<PathBuf>,
+    #[structopt(
+        long,
+        default_value = "catmull-rom",
+        help = "Between keyframes. One of \"linear\", \"catmull-rom\", \"bezier\". Bezier curves take two control points between each pair of keyframes"
+    )]
+    interpolation: Interpolation,
+    #[structopt(
+        long,
+        help = "Renders a sequence of frames, as \"first..end\", to \"<output>.<frame>.ppm\""
+    )]
+    frames: Option<Frames>,
+    #[structopt(long, default_value = "24")]
+    fps: f32,
 
     #[structopt(short, long, default_value = "50")]
     samples: usize,
+    #[structopt(
+        long,
+        help = "Sample adaptively, stopping pixels once their relative error is below this. The total number of samples stays the same"
+    )]
+    noise_threshold: Option<f32>,
+    #[structopt(
+        long,
+        default_value = "sobol",
+        help = "Where samples are placed. One of \"independent\", \"stratified\", \"halton\", \"sobol\", \"blue-noise\""
+    )]
+    sampler: SamplerName,
+    #[structopt(
+        long,
+        default_value = "box",
+        help = "How samples are weighted into pixels. One of \"box\", \"tent\", \"gaussian\", \"mitchell\", \"lanczos\""
+    )]
+    filter: filter::Shape,
+    #[structopt(long, help = "In pixels. Defaults to one suited to the filter")]
+    filter_radius: Option<f32>,
+    #[structopt(
+        long,
+        help = "Sample positions according to the filter, instead of splatting each sample into neighbouring pixels"
+    )]
+    filter_importance: bool,
+
+    #[structopt(
+        long,
+        default_value = "path",
+        help = "One of \"path\", \"bdpt\", \"sppm\", \"ao\", \"normals\", \"geometric-normals\", \"depth\", \"uv\", \"material-id\", \"primitive-id\", \"bvh-cost\""
+    )]
+    integrator: IntegratorName,
+    #[structopt(
+        long,
+        default_value = "50",
+        help = "Maximum number of bounces per path"
+    )]
+    max_depth: usize,
+    #[structopt(
+        long,
+        default_value = "3",
+        help = "Number of bounces before paths may be terminated by Russian roulette"
+    )]
+    roulette_depth: usize,
+    #[structopt(
+        long,
+        default_value = "100000",
+        help = "Photons traced per pass, for \"sppm\""
+    )]
+    photons: usize,
+    #[structopt(
+        long,
+        default_value = "0.1",
+        help = "Initial photon gather radius, for \"sppm\""
+    )]
+    photon_radius: f32,
+    #[structopt(
+        long,
+        default_value = "1.0",
+        help = "Distance within which surfaces occlude, for \"ao\""
+    )]
+    ao_radius: f32,
 
     #[structopt(short, long)]
     jobs: Option<usize>,
+    #[structopt(
+        long,
+        default_value = "0",
+        help = "Seeds the scene and the sampler. Renders with the same seed are identical, whatever the number of jobs"
+    )]
+    seed: u64,
+
+    #[structopt(
+        short,
+        long,
+        help = "Where to write the image, instead of standard output"
+    )]
+    output: Option<PathBuf>,
+    #[structopt(
+        long = "aov",
+        use_delimiter = true,
+        help = "Extra passes to write next to the output, with its extension replaced by \"<name>.pfm\". Any of \"albedo\", \"normal\", \"depth\", \"position\", \"diffuse-direct\", \"diffuse-indirect\", \"specular-direct\", \"specular-indirect\", \"emission\", \"samples\", \"samples-heatmap\""
+    )]
+    aovs: Vec<Aov>,
+    #[structopt(long, help = "Filter out noise, guided by albedo, normals and depth")]
+    denoise: bool,
+    #[structopt(
+        long,
+        require_delimiter = true,
+        help = "Renders only the pixels within \"x0,y0,x1,y1\", from the top left, framed as in the whole image. Values no greater than 1 are fractions of the image's size"
+    )]
+    crop: Vec<f32>,
+    #[structopt(
+        long,
+        help = "A PPM image as large as the whole image, to write the cropped pixels onto instead of on their own"
+    )]
+    composite: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Saves what has been rendered to this file every --checkpoint-interval, and once done, to be carried on with by --resume"
+    )]
+    checkpoint: Option<PathBuf>,
+    #[structopt(long, default_value = "300", help = "In seconds")]
+    checkpoint_interval: f32,
+    #[structopt(
+        long,
+        help = "Carries on with a render from a checkpoint, which is kept up to date unless --checkpoint is given. Other options should be as they were, though --samples can be raised to add more"
+    )]
+    resume: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Renders in passes of 1, 2, 4 and so on samples per pixel, up to --samples, rewriting the output after each"
+    )]
+    progressive: bool,
+    #[structopt(
+        long,
+        help = "In seconds. Also rewrites the output this often while rendering"
+    )]
+    update_interval: Option<f32>,
+    #[structopt(
+        long,
+        help = "In seconds. Renders progressively, stopping once the time is up and keeping what has been rendered"
+    )]
+    time_limit: Option<f32>,
 
     #[structopt(default_value = "field")]
     scene: SceneName,
 }
 
+impl Config {
+    fn lens(&self) -> Result<Lens, Box<dyn std::error::Error>> {
+        let aperture = match (&self.aperture_mask, self.aperture_blades) {
+            (Some(path), _) => Aperture::mask(path)?,
+            (None, Some(blades)) if blades < 3 => Err("An aperture needs at least 3 blades.")?,
+            (None, Some(blades)) => Aperture::Polygon {
+                blades,
+                rotation: self.aperture_rotation.to_radians(),
+            },
+            (None, None) => Aperture::Disk,
+        };
+        let tilt = pair(&self.lens_tilt, "--lens-tilt")?;
+        Ok(Lens {
+            aperture,
+            cat_eye: self.cat_eye,
+            shift: pair(&self.lens_shift, "--lens-shift")?,
+            tilt: (tilt.0.to_radians(), tilt.1.to_radians()),
+            chromatic_aberration: self.chromatic_aberration,
+        })
+    }
+
+    // The pixels to render, counted from the bottom left.
+    fn window(&self) -> Result<Window, Box<dyn std::error::Error>> {
+        let (width, height) = (self.width, self.height);
+        let (x0, y0, x1, y1) = match self.crop[..] {
+            [] => {
+                return Ok(Window {
+                    min: (0, 0),
+                    max: (width, height),
+                })
+            }
+            [x0, y0, x1, y1] if self.crop.iter().all(|&value| value <= 1.0) => (
+                x0 * width as f32,
+                y0 * height as f32,
+                x1 * width as f32,
+                y1 * height as f32,
+            ),
+            [x0, y0, x1, y1] => (x0, y0, x1, y1),
+            _ => Err("--crop takes four values.")?,
+        };
+        if x0 < 0.0 || y0 < 0.0 {
+            Err("--crop should be within the image.")?;
+        }
+        let (x0, y0) = (x0.round() as usize, y0.round() as usize);
+        let (x1, y1) = (x1.round() as usize, y1.round() as usize);
+        if x0 >= x1 || y0 >= y1 || x1 > width || y1 > height {
+            Err("--crop should be a non-empty rectangle within the image.")?;
+        }
+        Ok(Window {
+            min: (x0, height - y1),
+            max: (x1, height - y0),
+        })
+    }
+
+    fn physical_camera(&self) -> Option<Physical> {
+        self.focal_length.map(|focal_length| Physical {
+            focal_length,
+            sensor_width: self.sensor_width,
+            f_number: self.f_number,
+            shutter_time: self.shutter_time,
+            iso: self.iso,
+        })
+    }
+}
+
+// Two values given to an option as "x,y", or zeros if it wasn't given.
+fn pair(values: &[f32], option: &str) -> Result<(f32, f32), String> {
+    match values {
+        [] => Ok((0.0, 0.0)),
+        [x, y] => Ok((*x, *y)),
+        _ => Err(format!("{} takes two values.", option)),
+    }
+}
+
 enum SceneName {
     Field,
+    Materials,
+    Caustics,
 }
 
 impl SceneName {
-    fn make(&self) -> Scene {
+    fn make(&self, seed: u64) -> Scene {
         match self {
-            Self::Field => Scene::field(),
+            Self::Field => Scene::field(seed),
+            Self::Materials => Scene::materials(seed),
+            Self::Caustics => Scene::caustics(),
         }
     }
 
     fn camera_default_pos(&self) -> Vector {
         match self {
             Self::Field => Vector::new(13.0, 2.0, 3.0),
+            Self::Materials => Vector::new(0.0, 12.0, 22.0),
+            Self::Caustics => Vector::new(0.0, 3.0, 10.0),
         }
     }
 
     fn camera_default_target(&self) -> Vector {
         match self {
             Self::Field => Vector::new(0.0, 0.0, 0.0),
+            Self::Materials => Vector::new(0.0, 0.5, -2.5),
+            Self::Caustics => Vector::new(0.0, 0.8, 0.0),
+        }
+    }
+}
+
+enum IntegratorName {
+    Path,
+    Bidirectional,
+    PhotonMapping,
+    AmbientOcclusion,
+    Debug(DebugView),
+}
+
+impl IntegratorName {
+    fn make(&self, config: &Config) -> Arc<dyn Integrator> {
+        match self {
+            Self::Path => PathTracer::new(config.max_depth, config.roulette_depth),
+            Self::Bidirectional => Bidirectional::new(config.max_depth),
+            Self::PhotonMapping => {
+                PhotonMapper::new(config.max_depth, config.photons, config.photon_radius)
+            }
+            Self::AmbientOcclusion => AmbientOcclusion::new(config.ao_radius),
+            Self::Debug(view) => Debug::new(*view),
+        }
+    }
+}
+
+impl FromStr for IntegratorName {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "path" => Self::Path,
+            "bdpt" => Self::Bidirectional,
+            "sppm" => Self::PhotonMapping,
+            "ao" => Self::AmbientOcclusion,
+            "normals" => Self::Debug(DebugView::ShadingNormal),
+            "geometric-normals" => Self::Debug(DebugView::GeometricNormal),
+            "depth" => Self::Debug(DebugView::Depth),
+            "uv" => Self::Debug(DebugView::Uv),
+            "material-id" => Self::Debug(DebugView::MaterialId),
+            "primitive-id" => Self::Debug(DebugView::PrimitiveId),
+            "bvh-cost" => Self::Debug(DebugView::TraversalCost),
+            _ => Err("Unknown integrator.")?,
+        })
+    }
+}
+
+enum ProjectionName {
+    Perspective,
+    Orthographic,
+    Fisheye(Mapping),
+    Equirectangular,
+    CubeMap,
+}
+
+impl ProjectionName {
+    fn make(
+        &self,
+        config: &Config,
+        view: View,
+        fov: f32,
+        aspect_ratio: f32,
+        lens: &Lens,
+        system: Option<&LensSystem>,
+    ) -> Result<Arc<dyn Camera>, Box<dyn std::error::Error>> {
+        let (fov, aperture) = match config.physical_camera() {
+            Some(physical) => {
+                let mapping = match self {
+                    Self::Fisheye(mapping) => Some(*mapping),
+                    _ => None,
+                };
+                (
+                    physical.field_of_view(aspect_ratio, mapping),
+                    physical.aperture(),
+                )
+            }
+            None => (fov.to_radians(), config.camera_aperture),
+        };
+        Ok(match (self, system) {
+            (Self::Perspective, Some(system)) => {
+                Realistic::new(view, system.clone(), config.sensor_width, aspect_ratio)?
+            }
+            (_, Some(_)) => Err("Lens prescriptions need the perspective projection.")?,
+            (Self::Perspective, None) => {
+                Perspective::new(view, fov, aspect_ratio, aperture, lens.clone())
+            }
+            (Self::Orthographic, None) => Orthographic::new(view, fov, aspect_ratio),
+            (Self::Fisheye(mapping), None) => Fisheye::new(view, fov, aspect_ratio, *mapping),
+            (Self::Equirectangular, None) => Equirectangular::new(view),
+            (Self::CubeMap, None) => CubeMap::new(view),
+        })
+    }
+}
+
+impl FromStr for ProjectionName {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "perspective" => Self::Perspective,
+            "orthographic" => Self::Orthographic,
+            "fisheye-equidistant" => Self::Fisheye(Mapping::Equidistant),
+            "fisheye-equisolid" => Self::Fisheye(Mapping::Equisolid),
+            "equirectangular" => Self::Equirectangular,
+            "cube-map" => Self::CubeMap,
+            _ => Err("Unknown projection.")?,
+        })
+    }
+}
+
+enum SamplerName {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerName {
+    fn make(&self, seed: u64, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        match self {
+            Self::Independent => Independent::new(seed),
+            Self::Stratified => Stratified::new(seed, samples_per_pixel),
+            Self::Halton => Halton::new(seed),
+            Self::Sobol => Sobol::new(seed),
+            Self::BlueNoise => BlueNoise::new(seed, samples_per_pixel),
         }
     }
 }
 
+impl FromStr for SamplerName {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "independent" => Self::Independent,
+            "stratified" => Self::Stratified,
+            "halton" => Self::Halton,
+            "sobol" => Self::Sobol,
+            "blue-noise" => Self::BlueNoise,
+            _ => Err("Unknown sampler.")?,
+        })
+    }
+}
+
 impl FromStr for SceneName {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.to_lowercase().as_str() {
             "field" => Self::Field,
+            "materials" => Self::Materials,
+            "caustics" => Self::Caustics,
             _ => Err("Unknown scene.")?,
         })
     }
@@ -156,9 +557,7 @@ impl FromStr for SceneName {
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::from_args();
 
-    let world: Arc<dyn Surface> = Arc::new(config.scene.make());
-
-    let aspect_ratio = config.width as f32 / config.height as f32;
+    let scene = config.scene.make(config.seed);
 
     let from = config
         .camera_pos
@@ -166,57 +565,409 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let at = config
         .camera_target
         .unwrap_or(config.scene.camera_default_target());
+    let still = Keyframe {
+        time: 0.0,
+        position: from,
+        target: at,
+        fov: config.camera_fov,
+        focus_distance: config.camera_focus_distance.unwrap_or((from - at).length()),
+    };
+    let animation = match &config.keyframes {
+        Some(path) => Some(Animation::load(path, config.interpolation)?),
+        None => None,
+    };
+
+    let lens = config.lens()?;
+    let system = match &config.lens_file {
+        Some(path) => Some(LensSystem::load(path)?),
+        None => None,
+    };
+
+    if !config.aovs.is_empty() && config.output.is_none() {
+        Err("AOVs need an --output to be written next to.")?;
+    }
+    if config.noise_threshold.is_some() {
+        if let IntegratorName::PhotonMapping = config.integrator {
+            Err("Photon mapping can't sample adaptively.")?;
+        }
+    }
+    if config.checkpoint.is_some() || config.resume.is_some() {
+        if config.frames.is_some() {
+            Err("Checkpoints are for single images, not --frames.")?;
+        }
+        if let IntegratorName::PhotonMapping = config.integrator {
+            Err("Photon mapping can't be checkpointed.")?;
+        }
+    }
+    if config.progressive {
+        if config.output.is_none() {
+            Err("Progressive rendering needs an --output to rewrite.")?;
+        }
+        if let IntegratorName::PhotonMapping = config.integrator {
+            Err("Photon mapping is progressive already.")?;
+        }
+    }
+
+    match (&config.frames, &config.output) {
+        (Some(_), None) => Err("Frames need an --output to be numbered after.")?,
+        (Some(frames), Some(output)) => {
+            for frame in frames.0.clone() {
+                let time = frame as f32 / config.fps;
+                let key = animation
+                    .as_ref()
+                    .map_or(still, |animation| animation.at(time));
+                let camera = make_camera(&config, &key, &lens, system.as_ref())?;
+                let path = output.with_extension(format!("{:04}.ppm", frame));
+                render(&config, &scene, camera.as_ref(), Some(&path))?;
+            }
+        }
+        (None, output) => {
+            let key = animation
+                .as_ref()
+                .map_or(still, |animation| animation.at(0.0));
+            let camera = make_camera(&config, &key, &lens, system.as_ref())?;
+            render(&config, &scene, camera.as_ref(), output.as_deref())?;
+        }
+    }
 
-    let camera = Arc::new(Camera::new(
-        from,
-        at,
+    Ok(())
+}
+
+fn make_camera(
+    config: &Config,
+    key: &Keyframe,
+    lens: &Lens,
+    system: Option<&LensSystem>,
+) -> Result<Arc<dyn Camera>, Box<dyn std::error::Error>> {
+    let aspect_ratio = config.width as f32 / config.height as f32;
+    let view = View::new(
+        key.position,
+        key.target,
         Vector::new(0.0, 1.0, 0.0),
-        config.camera_fov.to_radians(),
-        aspect_ratio,
-        config.camera_aperture,
-        config.camera_focus_distance.unwrap_or((from - at).length()),
-    ));
-
-    let mut writer = BufWriter::new(io::stdout());
-
-    write!(writer, "P3\n")?;
-    write!(writer, "{} {}\n", config.width, config.height)?;
-    write!(writer, "255\n")?;
-
-    let jobs = config.jobs.unwrap_or(num_cpus::get());
-    let samples_per_pixel = config.samples / jobs;
-
-    let bars = MultiProgress::new();
-    let bar_style = ProgressStyle::default_bar()
-        .template("[{bar:60.white/white}] {pos}/{len} {msg}")
-        .progress_chars("=> ");
-
-    let handles: Vec<_> = (0..jobs)
-        .map(|_| {
-            let dim = (config.width, config.height);
-            let camera_ref = Arc::clone(&camera);
-            let world_ref = Arc::clone(&world);
-            let bar = bars.add(ProgressBar::new(
-                (config.width * config.height * samples_per_pixel as usize) as u64,
-            ));
-            bar.set_style(bar_style.clone());
-            thread::spawn(move || render(camera_ref, dim, world_ref, samples_per_pixel, bar))
-        })
-        .collect();
+        key.focus_distance,
+    );
+    Ok(match config.stereo {
+        Some(layout) => {
+            let eye_aspect_ratio = match layout {
+                Layout::SideBySide => aspect_ratio / 2.0,
+                Layout::TopBottom => aspect_ratio * 2.0,
+            };
+            Stereo::new(
+                config
+                    .projection
+                    .make(config, view, key.fov, eye_aspect_ratio, lens, system)?,
+                layout,
+                config.interocular_distance,
+                config.convergence_distance.unwrap_or(view.focus_distance()),
+                matches!(
+                    config.projection,
+                    ProjectionName::Equirectangular | ProjectionName::CubeMap
+                ),
+            )
+        }
+        None => config
+            .projection
+            .make(config, view, key.fov, aspect_ratio, lens, system)?,
+    })
+}
+
+// Renders an image and writes it to `output`, or to standard output, with any AOVs alongside.
+fn render(
+    config: &Config,
+    scene: &Scene,
+    camera: &dyn Camera,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let window = config.window()?;
+    let background: Option<Vec<Vector>> = match &config.composite {
+        Some(path) => {
+            let (width, height, pixels) = ppm::read(path)?;
+            if (width, height) != (config.width, config.height) {
+                Err("The image to composite onto should be as large as the whole image.")?;
+            }
+            Some(pixels)
+        }
+        None => None,
+    };
+
+    let mut film_aovs = config.aovs.clone();
+    if config.denoise {
+        for feature in &denoise::FEATURES {
+            if !film_aovs.contains(feature) {
+                film_aovs.push(*feature);
+            }
+        }
+    }
 
-    bars.join()?;
+    let filter = Filter::new(
+        config.filter,
+        config
+            .filter_radius
+            .unwrap_or(config.filter.default_radius()),
+        config.filter_importance,
+    );
+    let mut film = Film::new((config.width, config.height), window)
+        .with_filter(filter)
+        .with_aovs(&film_aovs);
+    if let Some(path) = &config.resume {
+        film.read_checkpoint(&mut BufReader::new(File::open(path)?))?;
+    }
 
-    let mut screen: Vec<Color> = [Default::default()].repeat(config.width * config.height);
-    handles.into_iter().map(|h| h.join()).for_each(|s| {
-        s.unwrap()
+    let sampled = film.sample_window();
+    let (width, height) = sampled.size();
+    let bar = ProgressBar::new((width * height * config.samples) as u64);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{bar:60.white/white}] {pos}/{len} {msg}")
+            .progress_chars("=> "),
+    );
+    let rendered: usize = (sampled.min.1..sampled.max.1)
+        .map(|row| film.rendered(row).min(config.samples))
+        .sum();
+    bar.set_position((rendered * width) as u64);
+
+    let checkpoint = config.checkpoint.as_ref().or(config.resume.as_ref());
+    let update = output.and(config.update_interval);
+    let save = |film: &Film| {
+        if let Some(path) = checkpoint {
+            if let Err(error) = save_checkpoint(film, path) {
+                bar.println(format!("Couldn't save a checkpoint: {}", error));
+            }
+        }
+        if update.is_some() {
+            if let Err(error) = write_image(config, film, background.as_deref(), output) {
+                bar.println(format!("Couldn't update the image: {}", error));
+            }
+        }
+    };
+    let interval = match (checkpoint, update) {
+        (Some(_), Some(update)) => Some(config.checkpoint_interval.min(update)),
+        (Some(_), None) => Some(config.checkpoint_interval),
+        (None, update) => update,
+    };
+    let deadline = config
+        .time_limit
+        .map(|limit| Instant::now() + Duration::from_secs_f32(limit));
+
+    // Progressive renders double the samples per pixel with each pass, up to the full number.
+    // Photon mapping refines its whole image with each pass anyway.
+    let progressive = (config.progressive || config.time_limit.is_some())
+        && !matches!(config.integrator, IntegratorName::PhotonMapping);
+    let passes: Vec<_> = if progressive {
+        (0..)
+            .map(|pass| 1 << pass)
+            .take_while(|&samples| samples < config.samples)
+            .chain(iter::once(config.samples))
+            .collect()
+    } else {
+        vec![config.samples]
+    };
+
+    let integrator = config.integrator.make(config);
+    let make_sampler = || config.sampler.make(config.seed, config.samples);
+    for samples_per_pixel in passes {
+        let options = RenderOptions {
+            samples_per_pixel,
+            noise_threshold: config.noise_threshold,
+            jobs: config.jobs.unwrap_or(num_cpus::get()),
+            sampler: &make_sampler,
+            checkpoint: interval.map(|interval| Checkpoint {
+                interval: Duration::from_secs_f32(interval),
+                save: &save,
+            }),
+            deadline,
+        };
+        integrator.render(scene, camera, &mut film, &options, &bar);
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+        if samples_per_pixel < config.samples && output.is_some() {
+            write_image(config, &film, background.as_deref(), output)?;
+        }
+    }
+    bar.finish_with_message("Done.");
+    if let Some(path) = checkpoint {
+        save_checkpoint(&film, path)?;
+    }
+
+    write_image(config, &film, background.as_deref(), output)
+}
+
+// Writes the film's image to `output`, or to standard output, with any AOVs alongside. A
+// `background` as large as the whole image is written around a cropped film.
+fn write_image(
+    config: &Config,
+    film: &Film,
+    background: Option<&[Vector]>,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let exposure = config
+        .physical_camera()
+        .map_or(1.0, |physical| physical.exposure());
+    let colors: Vec<_> = if config.denoise {
+        denoise::denoise(film)
+            .into_iter()
+            .map(|color| Color::from(color * exposure))
+            .collect()
+    } else {
+        film.pixels()
             .iter()
-            .enumerate()
-            .for_each(|(i, c)| screen[i].add_samples(c))
+            .map(|color| Color::from(color.scaled() * exposure))
+            .collect()
+    };
+
+    let window = film.window();
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
     });
-
-    for color in screen {
-        write!(writer, "{}\n", color)?;
+    writeln!(writer, "P3")?;
+    match background {
+        Some(background) => {
+            writeln!(writer, "{} {}", config.width, config.height)?;
+            writeln!(writer, "255")?;
+            let (width, _) = window.size();
+            for (index, pixel) in background.iter().enumerate() {
+                // Rows of the image go from the top down.
+                let (x, y) = (
+                    index % config.width,
+                    config.height - 1 - index / config.width,
+                );
+                if window.contains((x, y)) {
+                    let index = (window.max.1 - 1 - y) * width + x - window.min.0;
+                    writeln!(writer, "{}", colors[index])?;
+                } else {
+                    let byte = |channel: f32| (channel * 255.0).round() as u8;
+                    writeln!(
+                        writer,
+                        "{} {} {}",
+                        byte(pixel[0]),
+                        byte(pixel[1]),
+                        byte(pixel[2])
+                    )?;
+                }
+            }
+        }
+        None => {
+            let (width, height) = window.size();
+            writeln!(writer, "{} {}", width, height)?;
+            writeln!(writer, "255")?;
+            for color in &colors {
+                writeln!(writer, "{}", color)?;
+            }
+        }
+    }
+    writer.flush()?;
+
+    if let Some(output) = output {
+        for aov in &config.aovs {
+            let path = output.with_extension(format!("{}.pfm", aov.name()));
+            let mut file = BufWriter::new(File::create(path)?);
+            film.write_aov(*aov, &mut file)?;
+            file.flush()?;
+        }
     }
 
     Ok(())
 }
+
+// Writes the checkpoint next to where it belongs first, so that stopping part way through leaves
+// the last one whole.
+fn save_checkpoint(film: &Film, path: &Path) -> io::Result<()> {
+    let partial = path.with_extension("partial");
+    let mut file = BufWriter::new(File::create(&partial)?);
+    film.write_checkpoint(&mut file)?;
+    file.flush()?;
+    drop(file);
+    fs::rename(partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Renders the way `main` does, building the scene anew so that it lands at other addresses.
+    fn render_pixels(args: &[&str], jobs: usize) -> Vec<Vector> {
+        let jobs = jobs.to_string();
+        let config = Config::from_iter(["ray", "-j", &jobs].iter().chain(args));
+        let scene = config.scene.make(config.seed);
+        let from = config.scene.camera_default_pos();
+        let at = config.scene.camera_default_target();
+        let key = Keyframe {
+            time: 0.0,
+            position: from,
+            target: at,
+            fov: config.camera_fov,
+            focus_distance: (from - at).length(),
+        };
+        let camera = make_camera(&config, &key, &config.lens().unwrap(), None).unwrap();
+
+        let filter = Filter::new(config.filter, config.filter.default_radius(), false);
+        let mut film =
+            Film::new((config.width, config.height), config.window().unwrap()).with_filter(filter);
+        let make_sampler = || config.sampler.make(config.seed, config.samples);
+        let options = RenderOptions {
+            samples_per_pixel: config.samples,
+            noise_threshold: config.noise_threshold,
+            jobs: config.jobs.unwrap(),
+            sampler: &make_sampler,
+            checkpoint: None,
+            deadline: None,
+        };
+        config.integrator.make(&config).render(
+            &scene,
+            camera.as_ref(),
+            &mut film,
+            &options,
+            &ProgressBar::hidden(),
+        );
+        film.pixels().iter().map(|pixel| **pixel).collect()
+    }
+
+    fn bits(pixels: &[Vector]) -> Vec<[u32; 3]> {
+        pixels
+            .iter()
+            .map(|pixel| [pixel[0].to_bits(), pixel[1].to_bits(), pixel[2].to_bits()])
+            .collect()
+    }
+
+    #[test]
+    fn renders_are_the_same_whatever_the_number_of_jobs() {
+        let renders: &[&[&str]] = &[
+            &["--integrator", "path", "materials"],
+            &["--integrator", "bdpt", "caustics"],
+            &["--integrator", "sppm", "--photons", "2000", "caustics"],
+            &["--integrator", "ao", "materials"],
+            &["--integrator", "material-id", "materials"],
+            &["--integrator", "primitive-id", "materials"],
+            &["--noise-threshold", "0.1", "--filter", "gaussian", "field"],
+        ];
+        for args in renders {
+            let args: Vec<_> = ["-w", "24", "-h", "16", "-s", "4", "--seed", "3"]
+                .iter()
+                .chain(args.iter())
+                .copied()
+                .collect();
+            let single = bits(&render_pixels(&args, 1));
+            assert_eq!(single, bits(&render_pixels(&args, 4)), "{:?}", args);
+        }
+    }
+
+    fn window(crop: &str) -> Option<((usize, usize), (usize, usize))> {
+        let crop = format!("--crop={}", crop);
+        let config = Config::from_iter(&["ray", "-w", "40", "-h", "20", &crop]);
+        let window = config.window().ok()?;
+        Some((window.min, window.max))
+    }
+
+    #[test]
+    fn crop_windows_are_parsed() {
+        // From the top left, but held from the bottom left.
+        assert_eq!(window("4,2,10,8").unwrap(), ((4, 12), (10, 18)));
+        assert_eq!(window("0.25,0.5,1,1").unwrap(), ((10, 0), (40, 10)));
+        assert_eq!(window("0,0,40,20").unwrap(), ((0, 0), (40, 20)));
+        for crop in &["1,2,3", "-1,0,4,4", "4,4,4,8", "10,2,4,8", "0,0,41,20"] {
+            assert!(window(crop).is_none(), "{}", crop);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::Vector;
+
+// Reads a PPM image, either plain or binary, with channels from 0 to 1, top row first.
+pub fn read(path: &Path) -> Result<(usize, usize, Vec<Vector>), Box<dyn Error>> {
+    let bytes = fs::read(path)?;
+    let mut position = 0;
+
+    let binary = match token(&bytes, &mut position)?.as_str() {
+        "P3" => false,
+        "P6" => true,
+        _ => Err("Only PPM images are supported.")?,
+    };
+    let width: usize = token(&bytes, &mut position)?.parse()?;
+    let height: usize = token(&bytes, &mut position)?.parse()?;
+    let max: f32 = token(&bytes, &mut position)?.parse()?;
+    if width == 0 || height == 0 || max > 255.0 {
+        Err("Unsupported PPM image.")?;
+    }
+
+    let count = 3 * width * height;
+    let channels = if binary {
+        // A single whitespace character separates the header from the pixels.
+        let start = position + 1;
+        bytes
+            .get(start..start + count)
+            .ok_or("Unexpected end of image.")?
+            .iter()
+            .map(|&byte| byte as f32)
+            .collect::<Vec<_>>()
+    } else {
+        (0..count)
+            .map(|_| Ok(token(&bytes, &mut position)?.parse::<f32>()?))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+    };
+    let pixels = channels
+        .chunks(3)
+        .map(|pixel| Vector::new(pixel[0], pixel[1], pixel[2]) / max)
+        .collect();
+    Ok((width, height, pixels))
+}
+
+// The next word of a PPM header or plain PPM image, skipping comments.
+fn token(bytes: &[u8], position: &mut usize) -> Result<String, Box<dyn Error>> {
+    loop {
+        match bytes.get(*position) {
+            Some(b'#') => {
+                while bytes.get(*position).is_some_and(|&byte| byte != b'\n') {
+                    *position += 1;
+                }
+            }
+            Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+            Some(_) => break,
+            None => Err("Unexpected end of image.")?,
+        }
+    }
+    let start = *position;
+    while bytes
+        .get(*position)
+        .is_some_and(|byte| !byte.is_ascii_whitespace())
+    {
+        *position += 1;
+    }
+    Ok(String::from_utf8_lossy(&bytes[start..*position]).into_owned())
+}
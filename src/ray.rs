@@ -1,5 +1,6 @@
 use crate::vector::Vector;
 
+#[derive(Copy, Clone)]
 pub struct Ray {
     origin: Vector,
     direction: Vector,
@@ -0,0 +1,75 @@
+use super::{permute, Position, Sampler};
+
+// Inverse of the golden ratio, which spreads a Fibonacci-like lattice evenly for any sample count.
+const GOLDEN: f32 = 0.618_034;
+// Generators of the R2 sequence (Roberts 2018), whose values over the pixel grid form blue noise.
+const R2: (f32, f32) = (0.754_877_7, 0.569_840_3);
+
+// A rank-1 lattice within each pixel, shifted per pixel by an R2 dither so that the remaining
+// error is spread over the image as blue noise instead of white. Samples past the lattice size are
+// uniformly random.
+pub struct BlueNoise {
+    position: Position,
+    samples_per_pixel: usize,
+}
+
+impl BlueNoise {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(seed: u64, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        Box::new(Self {
+            position: Position::new(seed),
+            samples_per_pixel: samples_per_pixel.max(1),
+        })
+    }
+
+    // The lattice point to use for the next dimension, and the pixel's shift along each axis.
+    // Both vary with the dimension so that dimensions aren't correlated, but not with the pixel,
+    // so that neighbouring pixels keep the pattern of the dither.
+    fn next_point(&mut self) -> Option<(f32, (f32, f32))> {
+        let key = self.position.next_dimension_key();
+        if self.position.index >= self.samples_per_pixel {
+            return None;
+        }
+
+        let (x, y) = (self.position.pixel.0 as f32, self.position.pixel.1 as f32);
+        let offset = (
+            (key >> 48) as f32 / (1 << 16) as f32,
+            ((key >> 32) & 0xffff) as f32 / (1 << 16) as f32,
+        );
+        let shift = (
+            0.5 + x * R2.0 + y * R2.1 + offset.0,
+            0.5 + x * R2.1 + y * R2.0 + offset.1,
+        );
+        let count = self.samples_per_pixel as u32;
+        let index = permute(self.position.index as u32, count, key as u32);
+        Some((index as f32, shift))
+    }
+}
+
+fn fract(x: f32) -> f32 {
+    // Rounding can land exactly on 1.
+    (x - x.floor()).min(1.0 - f32::EPSILON)
+}
+
+impl Sampler for BlueNoise {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.position.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        match self.next_point() {
+            Some((index, shift)) => fract((index + 0.5) / self.samples_per_pixel as f32 + shift.0),
+            None => self.position.rng.next_f32(),
+        }
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        match self.next_point() {
+            Some((index, shift)) => (
+                fract((index + 0.5) / self.samples_per_pixel as f32 + shift.0),
+                fract(index * GOLDEN + shift.1),
+            ),
+            None => (self.position.rng.next_f32(), self.position.rng.next_f32()),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::{hash, permute, Position, Sampler};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+// The Halton sequence within each pixel, one prime base per dimension, Owen-scrambled per pixel
+// and dimension. Without scrambling, bases close to each other give points along a few lines.
+// Dimensions past the last prime are uniformly random.
+pub struct Halton {
+    position: Position,
+}
+
+impl Halton {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(seed: u64) -> Box<dyn Sampler> {
+        Box::new(Self {
+            position: Position::new(seed),
+        })
+    }
+}
+
+// The digits of `index` in `base`, mirrored about the decimal point. Each digit is permuted
+// depending on the digits before it, which is enough to Owen scramble the result.
+fn scrambled_radical_inverse(base: u32, mut index: u64, seed: u64) -> f32 {
+    let inverse_base = 1.0 / base as f64;
+    let mut factor = inverse_base;
+    let mut prefix = 0;
+    let mut ret = 0.0;
+    // Past f32 precision, more digits make no difference.
+    while factor > 1e-8 {
+        let digit = (index % base as u64) as u32;
+        let key = hash(&[seed, prefix]) as u32;
+        ret += permute(digit, base, key) as f64 * factor;
+        prefix = prefix * base as u64 + digit as u64 + 1;
+        index /= base as u64;
+        factor *= inverse_base;
+    }
+    ret as f32
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.position.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let dimension = self.position.dimension;
+        let key = self.position.next_key();
+        let base = match PRIMES.get(dimension) {
+            Some(&base) => base,
+            None => return self.position.rng.next_f32(),
+        };
+
+        // Rounding can land exactly on 1.
+        scrambled_radical_inverse(base, self.position.index as u64, key).min(1.0 - f32::EPSILON)
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.next_1d(), self.next_1d())
+    }
+}
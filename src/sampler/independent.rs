@@ -0,0 +1,29 @@
+use super::{Position, Sampler};
+
+// Uniform random numbers, with no attempt to spread them out.
+pub struct Independent {
+    position: Position,
+}
+
+impl Independent {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(seed: u64) -> Box<dyn Sampler> {
+        Box::new(Self {
+            position: Position::new(seed),
+        })
+    }
+}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.position.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        self.position.rng.next_f32()
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        (self.position.rng.next_f32(), self.position.rng.next_f32())
+    }
+}
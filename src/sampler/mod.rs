@@ -0,0 +1,281 @@
+mod blue_noise;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+
+pub use blue_noise::BlueNoise;
+pub use halton::Halton;
+pub use independent::Independent;
+pub use sobol::Sobol;
+pub use stratified::Stratified;
+
+use std::f32::consts::PI;
+
+use crate::Vector;
+
+// Source of the random numbers used to render. Each call takes the next dimension of the current
+// sample, so that samplers can spread the samples of a pixel evenly in every dimension.
+pub trait Sampler: Send {
+    // Starts sample number `index` of `pixel`.
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize);
+
+    // In [0, 1).
+    fn next_1d(&mut self) -> f32;
+
+    fn next_2d(&mut self) -> (f32, f32);
+
+    fn unit_vector(&mut self) -> Vector {
+        let (u, v) = self.next_2d();
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Vector::new(r * phi.cos(), r * phi.sin(), z)
+    }
+
+    fn in_unit_sphere(&mut self) -> Vector {
+        self.unit_vector() * self.next_1d().cbrt()
+    }
+
+    // Concentric mapping (Shirley and Chiu 1997), which keeps strata intact.
+    fn in_unit_disk(&mut self) -> Vector {
+        let (u, v) = self.next_2d();
+        let (x, y) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if x == 0.0 && y == 0.0 {
+            return Vector::new(0.0, 0.0, 0.0);
+        }
+        let (r, theta) = if x.abs() > y.abs() {
+            (x, PI / 4.0 * (y / x))
+        } else {
+            (y, PI / 2.0 - PI / 4.0 * (x / y))
+        };
+        Vector::new(r * theta.cos(), r * theta.sin(), 0.0)
+    }
+}
+
+// SplitMix64's finalizer.
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xbf58476d1ce4e5b9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+pub fn hash(values: &[u64]) -> u64 {
+    values
+        .iter()
+        .fold(0x9e3779b97f4a7c15, |acc, &value| mix(acc ^ mix(value)))
+}
+
+// Permuted congruential generator (O'Neill 2014), PCG-XSH-RR with 32 bit output.
+#[derive(Copy, Clone)]
+pub struct Pcg32 {
+    state: u64,
+    increment: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut ret = Self {
+            state: 0,
+            increment: (stream << 1) | 1,
+        };
+        ret.next_u32();
+        ret.state = ret.state.wrapping_add(seed);
+        ret.next_u32();
+        ret
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+
+    pub fn next_f32(&mut self) -> f32 {
+        // The top 24 bits, so that the result is never rounded up to 1.
+        (self.next_u32() >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+// Element `index` of a random permutation of `0..length`, chosen by `seed` (Kensler 2013).
+fn permute(mut index: u32, length: u32, seed: u32) -> u32 {
+    if length <= 1 {
+        return 0;
+    }
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    loop {
+        index ^= seed;
+        index = index.wrapping_mul(0xe170893d);
+        index ^= seed >> 16;
+        index ^= (index & mask) >> 4;
+        index ^= seed >> 8;
+        index = index.wrapping_mul(0x0929eb3f);
+        index ^= seed >> 23;
+        index ^= (index & mask) >> 1;
+        index = index.wrapping_mul(1 | seed >> 27);
+        index = index.wrapping_mul(0x6935fa69);
+        index ^= (index & mask) >> 11;
+        index = index.wrapping_mul(0x74dcb303);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0x9e501cc3);
+        index ^= (index & mask) >> 2;
+        index = index.wrapping_mul(0xc860a3df);
+        index &= mask;
+        index ^= index >> 5;
+        if index < length {
+            break;
+        }
+    }
+    index.wrapping_add(seed) % length
+}
+
+// State shared by all samplers: where they are, and a generator for anything left over.
+struct Position {
+    seed: u64,
+    pixel: (usize, usize),
+    index: usize,
+    dimension: usize,
+    rng: Pcg32,
+}
+
+impl Position {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+            rng: Pcg32::new(seed, 0),
+        }
+    }
+
+    fn start(&mut self, pixel: (usize, usize), index: usize) {
+        self.pixel = pixel;
+        self.index = index;
+        self.dimension = 0;
+        self.rng = Pcg32::new(
+            hash(&[self.seed, pixel.0 as u64, pixel.1 as u64, index as u64]),
+            self.seed,
+        );
+    }
+
+    // A hash of the next dimension alone, which is then used up.
+    fn next_dimension_key(&mut self) -> u64 {
+        let key = hash(&[self.seed, self.dimension as u64]);
+        self.dimension += 1;
+        key
+    }
+
+    // A hash of the pixel and the next dimension, which is then used up.
+    fn next_key(&mut self) -> u64 {
+        let key = hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+        ]);
+        self.dimension += 1;
+        key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Values of one dimension over the first `count` samples of a pixel.
+    fn dimension(sampler: &mut dyn Sampler, count: usize, dimension: usize) -> Vec<f32> {
+        (0..count)
+            .map(|index| {
+                sampler.start_pixel_sample((3, 5), index);
+                for _ in 0..dimension {
+                    sampler.next_1d();
+                }
+                sampler.next_1d()
+            })
+            .collect()
+    }
+
+    // Whether every one of `count` equal strata holds exactly one value.
+    fn stratified(values: &[f32], count: usize) -> bool {
+        let mut filled = vec![false; count];
+        for value in values {
+            let stratum = (value * count as f32) as usize;
+            if filled[stratum] {
+                return false;
+            }
+            filled[stratum] = true;
+        }
+        filled.iter().all(|&filled| filled)
+    }
+
+    #[test]
+    fn samples_are_within_the_unit_interval() {
+        let samplers: Vec<Box<dyn Sampler>> = vec![
+            Independent::new(1),
+            Stratified::new(1, 16),
+            Halton::new(1),
+            Sobol::new(1),
+            BlueNoise::new(1, 16),
+        ];
+        for mut sampler in samplers {
+            for pixel in 0..8 {
+                // Past the samples per pixel too, where some samplers fall back to random.
+                for index in 0..32 {
+                    sampler.start_pixel_sample((pixel, 2 * pixel), index);
+                    for _ in 0..40 {
+                        let value = sampler.next_1d();
+                        let (u, v) = sampler.next_2d();
+                        for value in &[value, u, v] {
+                            assert!((0.0..1.0).contains(value), "{}", value);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn samples_of_a_pixel_are_stratified_in_each_dimension() {
+        for d in 0..6 {
+            assert!(stratified(
+                &dimension(Stratified::new(1, 16).as_mut(), 16, d),
+                16
+            ));
+            assert!(stratified(&dimension(Sobol::new(1).as_mut(), 16, d), 16));
+            assert!(stratified(
+                &dimension(BlueNoise::new(1, 16).as_mut(), 16, d),
+                16
+            ));
+        }
+        // Halton strata follow the base of each dimension.
+        assert!(stratified(&dimension(Halton::new(1).as_mut(), 16, 0), 16));
+        assert!(stratified(&dimension(Halton::new(1).as_mut(), 9, 1), 9));
+        assert!(stratified(&dimension(Halton::new(1).as_mut(), 25, 2), 25));
+    }
+
+    #[test]
+    fn pairs_of_a_pixel_fill_a_grid() {
+        let samplers: Vec<Box<dyn Sampler>> = vec![Stratified::new(1, 16), Sobol::new(1)];
+        for mut sampler in samplers {
+            let mut cells = [false; 16];
+            for index in 0..16 {
+                sampler.start_pixel_sample((3, 5), index);
+                let (u, v) = sampler.next_2d();
+                cells[(v * 4.0) as usize * 4 + (u * 4.0) as usize] = true;
+            }
+            assert!(cells.iter().all(|&cell| cell));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Position, Sampler};
+
+// The first two dimensions of the Sobol sequence, Owen-scrambled and padded to any number of
+// dimensions by shuffling the sample order per dimension (Burley 2020).
+pub struct Sobol {
+    position: Position,
+}
+
+impl Sobol {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(seed: u64) -> Box<dyn Sampler> {
+        Box::new(Self {
+            position: Position::new(seed),
+        })
+    }
+}
+
+fn sobol(mut index: u32, dimension: usize) -> u32 {
+    let mut ret = 0;
+    let mut direction = 1 << 31;
+    while index != 0 {
+        if index & 1 != 0 {
+            ret ^= direction;
+        }
+        index >>= 1;
+        direction = match dimension {
+            0 => direction >> 1,
+            // Primitive polynomial x + 1.
+            _ => direction ^ (direction >> 1),
+        };
+    }
+    ret
+}
+
+// Random permutation of the bits from the least significant up, where each bit is flipped based
+// on those below it (Laine and Karras 2011).
+fn laine_karras(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+// Owen scrambling: each bit is flipped based on the more significant ones.
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras(x.reverse_bits(), seed).reverse_bits()
+}
+
+fn to_unit(x: u32) -> f32 {
+    (x >> 8) as f32 / (1 << 24) as f32
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.position.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let key = self.position.next_key();
+        let index = nested_uniform_scramble(self.position.index as u32, key as u32);
+        to_unit(nested_uniform_scramble(sobol(index, 0), (key >> 32) as u32))
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let key = self.position.next_key();
+        let index = nested_uniform_scramble(self.position.index as u32, key as u32);
+        let seed = super::mix(key);
+        (
+            to_unit(nested_uniform_scramble(sobol(index, 0), seed as u32)),
+            to_unit(nested_uniform_scramble(
+                sobol(index, 1),
+                (seed >> 32) as u32,
+            )),
+        )
+    }
+}
@@ -0,0 +1,59 @@
+use super::{permute, Position, Sampler};
+
+// Jittered samples, one per stratum of every dimension. The strata are visited in a different
+// random order for each dimension and pixel, so that dimensions aren't correlated.
+pub struct Stratified {
+    position: Position,
+    samples_per_pixel: usize,
+    // Strata along each axis in two dimensions.
+    grid: (usize, usize),
+}
+
+impl Stratified {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(seed: u64, samples_per_pixel: usize) -> Box<dyn Sampler> {
+        let columns = (samples_per_pixel as f32).sqrt().ceil().max(1.0) as usize;
+        let rows = samples_per_pixel.div_ceil(columns);
+        Box::new(Self {
+            position: Position::new(seed),
+            samples_per_pixel,
+            grid: (columns, rows.max(1)),
+        })
+    }
+
+    // Which stratum out of `count` the current sample falls in for the next dimension, if any.
+    fn stratum(&mut self, count: usize) -> Option<usize> {
+        let key = self.position.next_key();
+        if self.position.index >= self.samples_per_pixel {
+            return None;
+        }
+        Some(permute(self.position.index as u32, count as u32, key as u32) as usize)
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, pixel: (usize, usize), index: usize) {
+        self.position.start(pixel, index);
+    }
+
+    fn next_1d(&mut self) -> f32 {
+        let count = self.samples_per_pixel;
+        let jitter = self.position.rng.next_f32();
+        match self.stratum(count) {
+            Some(stratum) => (stratum as f32 + jitter) / count as f32,
+            None => jitter,
+        }
+    }
+
+    fn next_2d(&mut self) -> (f32, f32) {
+        let (columns, rows) = self.grid;
+        let jitter = (self.position.rng.next_f32(), self.position.rng.next_f32());
+        match self.stratum(columns * rows) {
+            Some(stratum) => (
+                ((stratum % columns) as f32 + jitter.0) / columns as f32,
+                ((stratum / columns) as f32 + jitter.1) / rows as f32,
+            ),
+            None => jitter,
+        }
+    }
+}
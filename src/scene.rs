@@ -1,45 +1,77 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use rand::distributions::{Distribution, Uniform};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
 
-use crate::surface::{material, Hit, Material, Sphere, Surface, SurfaceList};
-use crate::{random_unit, Color, Ray, Vector};
+use crate::light::Light;
+use crate::sampler::Sampler;
+use crate::surface::{material, Hit, Material, Mesh, Sphere, Surface, SurfaceList};
+use crate::texture;
+use crate::{Color, Ray, Vector};
+
+type MaterialFactory<'a> = Box<dyn Fn(&mut StdRng) -> Arc<dyn Material> + 'a>;
+
+pub enum Background {
+    Sky,
+    Solid(Color),
+}
 
 pub struct Scene {
     surfaces: SurfaceList<'static>,
+    lights: Vec<Light>,
+    background: Background,
+    // Index of each material, keyed by its address, in the order surfaces were added.
+    material_ids: HashMap<usize, usize>,
 }
 
 impl Scene {
-    pub fn field() -> Self {
+    fn new(surfaces: Vec<Box<dyn Surface>>, lights: Vec<Light>, background: Background) -> Self {
+        let mut material_ids = HashMap::new();
+        for material in surfaces.iter().filter_map(|surface| surface.material()) {
+            let next = material_ids.len();
+            material_ids.entry(address(material)).or_insert(next);
+        }
+        Scene {
+            surfaces: SurfaceList::new(surfaces),
+            lights,
+            background,
+            material_ids,
+        }
+    }
+
+    pub fn field(seed: u64) -> Self {
         let mut surfaces: Vec<Box<dyn Surface>> = Vec::with_capacity(500);
 
         let metal_color_dist = Uniform::new(0.5, 1.0);
         let fuzz_dist = Uniform::new(0.0, 0.5);
 
-        let mut rng = rand::thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
 
         let mat_glass = material::Dielectric::new(1.5);
 
-        let make_materials: [(_, Box<dyn Fn() -> Arc<dyn Material>>); 3] = [
+        let make_materials: [(_, MaterialFactory); 3] = [
             (
                 0.8,
-                Box::new(|| {
+                Box::new(|rng| {
                     material::Lambertian::new(
-                        (Vector::random_in_unit_range() * Vector::random_in_unit_range()).into(),
+                        (Vector::random_in_unit_range(rng) * Vector::random_in_unit_range(rng))
+                            .into(),
                     )
                 }),
             ),
             (
                 0.15,
-                Box::new(|| {
+                Box::new(|rng| {
                     material::Metal::new(
-                        Vector::random(&metal_color_dist).into(),
-                        fuzz_dist.sample(&mut rand::thread_rng()),
+                        Vector::random(&metal_color_dist, rng).into(),
+                        fuzz_dist.sample(rng),
                     )
                 }),
             ),
-            (0.05, Box::new(|| Arc::clone(&mat_glass))),
+            (0.05, Box::new(|_| Arc::clone(&mat_glass))),
         ];
 
         surfaces.push(Sphere::new(
@@ -51,16 +83,17 @@ impl Scene {
         for x in -11..11 {
             for z in -11..11 {
                 let center = Vector::new(
-                    x as f32 + 0.9 * random_unit(),
+                    x as f32 + 0.9 * rng.gen::<f32>(),
                     0.2,
-                    z as f32 + 0.9 * random_unit(),
+                    z as f32 + 0.9 * rng.gen::<f32>(),
                 );
 
                 if (center - Vector::new(4.0, 0.2, 0.0)).length() <= 0.9 {
                     continue;
                 }
 
-                let mat = make_materials.choose_weighted(&mut rng, |m| m.0).unwrap().1();
+                let make_material = &make_materials.choose_weighted(&mut rng, |m| m.0).unwrap().1;
+                let mat = make_material(&mut rng);
                 surfaces.push(Sphere::new(center, 0.2, &mat));
             }
         }
@@ -79,14 +112,250 @@ impl Scene {
             &material::Metal::new(Color::new(0.7, 0.6, 0.5), 0.0),
         ));
 
-        Scene {
-            surfaces: SurfaceList::new(surfaces),
+        Scene::new(surfaces, Vec::new(), Background::Sky)
+    }
+
+    pub fn materials(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let materials: Vec<Arc<dyn Material>> = vec![
+            // Soap bubble.
+            material::ThinFilm::new(
+                material::Dielectric::new(1.0),
+                380.0,
+                1.33,
+                1.0,
+                material::FilmMode::Spectral,
+            ),
+            // Oil slick.
+            material::ThinFilm::new(
+                material::Lambertian::new(Color::new(0.05, 0.05, 0.05)),
+                450.0,
+                1.45,
+                1.33,
+                material::FilmMode::Spectral,
+            ),
+            // Tempered steel.
+            material::ThinFilm::new(
+                material::Metal::new(Color::new(0.6, 0.6, 0.6), 0.05),
+                250.0,
+                2.2,
+                2.5,
+                material::FilmMode::Rgb,
+            ),
+            // Varnished wood.
+            material::Coated::new(
+                material::Mix::with_mask(
+                    material::Lambertian::new(Color::new(0.45, 0.25, 0.1)),
+                    material::Lambertian::new(Color::new(0.3, 0.15, 0.05)),
+                    texture::Checker::new(
+                        Arc::new(Color::new(0.0, 0.0, 0.0)),
+                        Arc::new(Color::new(1.0, 1.0, 1.0)),
+                        16.0,
+                    ),
+                ),
+                1.5,
+                Color::new(0.95, 0.85, 0.6),
+            ),
+            // Car paint.
+            material::Coated::new(
+                material::Mix::new(
+                    material::Lambertian::new(Color::new(0.6, 0.05, 0.05)),
+                    material::Metal::new(Color::new(0.8, 0.3, 0.3), 0.3),
+                    0.3,
+                ),
+                1.5,
+                Color::new(1.0, 1.0, 1.0),
+            ),
+            // Skin.
+            material::Subsurface::new(
+                Color::new(0.95, 0.8, 0.7),
+                Vector::new(0.35, 0.15, 0.08),
+                1.4,
+                material::SubsurfaceMode::RandomWalk,
+            ),
+            // Wax.
+            material::Subsurface::new(
+                Color::new(0.98, 0.95, 0.8),
+                Vector::new(0.2, 0.15, 0.1),
+                1.45,
+                material::SubsurfaceMode::RandomWalk,
+            ),
+            // Marble.
+            material::Subsurface::new(
+                Color::new(0.99, 0.99, 0.98),
+                Vector::new(0.05, 0.05, 0.05),
+                1.5,
+                material::SubsurfaceMode::RandomWalk,
+            ),
+            // Milk, previewed with the diffusion approximation.
+            material::Subsurface::new(
+                Color::new(0.999, 0.998, 0.99),
+                Vector::new(0.02, 0.02, 0.015),
+                1.35,
+                material::SubsurfaceMode::Diffusion,
+            ),
+            // Hammered copper.
+            material::Bump::new(
+                material::Metal::new(Color::new(0.95, 0.64, 0.54), 0.1),
+                texture::Noise::new(4.0, &mut rng),
+                0.05,
+            ),
+            // Quilted leather.
+            material::NormalMap::new(
+                material::Lambertian::new(Color::new(0.3, 0.12, 0.08)),
+                texture::Checker::new(
+                    Arc::new(Color::new(0.8, 0.5, 0.9)),
+                    Arc::new(Color::new(0.5, 0.8, 0.9)),
+                    24.0,
+                ),
+            ),
+            // Wire fence.
+            material::Cutout::new(
+                material::Metal::new(Color::new(0.7, 0.7, 0.7), 0.3),
+                texture::Checker::new(
+                    Arc::new(Color::new(0.0, 0.0, 0.0)),
+                    Arc::new(Color::new(1.0, 1.0, 1.0)),
+                    20.0,
+                ),
+                material::AlphaMode::Threshold(0.5),
+            ),
+            // Moth-eaten cloth.
+            material::Cutout::new(
+                material::Lambertian::new(Color::new(0.2, 0.3, 0.6)),
+                texture::Noise::new(3.0, &mut rng),
+                material::AlphaMode::Stochastic,
+            ),
+        ];
+
+        let position = |i: usize| {
+            let (row, column) = (i / 5, i % 5);
+            Vector::new(column as f32 * 2.2 - 4.4, 1.0, row as f32 * -2.5)
+        };
+
+        let mut surfaces: Vec<Box<dyn Surface>> = Vec::with_capacity(materials.len() + 2);
+        surfaces.push(Sphere::new(
+            Vector::new(0.0, -1000.0, 0.0),
+            1000.0,
+            &material::Lambertian::new(Color::new(0.5, 0.5, 0.5)),
+        ));
+
+        for (i, mat) in materials.iter().enumerate() {
+            surfaces.push(Sphere::new(position(i), 1.0, mat));
         }
+
+        // Rock, with true displacement.
+        surfaces.push(
+            Mesh::uv_sphere(
+                position(materials.len()),
+                0.9,
+                32,
+                &material::Lambertian::new(Color::new(0.4, 0.38, 0.35)),
+            )
+            .tessellate(2)
+            .displace(texture::Noise::new(1.5, &mut rng).as_ref(), 0.25)
+            .build(),
+        );
+
+        Scene::new(surfaces, Vec::new(), Background::Sky)
+    }
+
+    pub fn caustics() -> Self {
+        let (light, light_surface) = Light::sphere(
+            Vector::new(-1.5, 5.0, 1.5),
+            0.25,
+            Color::new(60.0, 55.0, 45.0),
+        );
+
+        let surfaces: Vec<Box<dyn Surface>> = vec![
+            Sphere::new(
+                Vector::new(0.0, -1000.0, 0.0),
+                1000.0,
+                &material::Lambertian::new(Color::new(0.7, 0.7, 0.7)),
+            ),
+            Sphere::new(
+                Vector::new(0.0, 0.0, -1010.0),
+                1000.0,
+                &material::Lambertian::new(Color::new(0.6, 0.6, 0.7)),
+            ),
+            Sphere::new(
+                Vector::new(0.0, 1.0, 0.0),
+                1.0,
+                &material::Dielectric::new(1.5),
+            ),
+            Sphere::new(
+                Vector::new(2.5, 0.7, -1.0),
+                0.7,
+                &material::Metal::new(Color::new(0.9, 0.8, 0.6), 0.0),
+            ),
+            Sphere::new(
+                Vector::new(-2.5, 0.6, -0.5),
+                0.6,
+                &material::Lambertian::new(Color::new(0.7, 0.15, 0.1)),
+            ),
+            light_surface,
+        ];
+
+        Scene::new(
+            surfaces,
+            vec![light],
+            Background::Solid(Color::new(0.0, 0.0, 0.0)),
+        )
+    }
+
+    // Stays the same between runs, unlike the material's address.
+    pub fn material_id(&self, material: &dyn Material) -> Option<usize> {
+        self.material_ids.get(&address(material)).copied()
     }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    // One of the lights, chosen uniformly.
+    pub fn sample_light(&self, sampler: &mut dyn Sampler) -> Option<&Light> {
+        if self.lights.is_empty() {
+            None
+        } else {
+            let i = (sampler.next_1d() * self.lights.len() as f32) as usize;
+            Some(&self.lights[i.min(self.lights.len() - 1)])
+        }
+    }
+
+    pub fn light_at(&self, point: &Vector) -> Option<&Light> {
+        self.lights.iter().find(|light| light.contains(point))
+    }
+
+    // Whether nothing blocks the segment between two points.
+    pub fn visible(&self, from: &Vector, to: &Vector) -> bool {
+        let direction = *to - *from;
+        let distance = direction.length();
+        self.hit(
+            &Ray::new(*from, direction / distance),
+            (1e-3, distance - 1e-3),
+        )
+        .is_none()
+    }
+
+    pub fn background(&self, ray: &Ray) -> Vector {
+        match self.background {
+            Background::Sky => {
+                let direction = ray.direction().unit();
+                let t = (direction[1] + 1.0) / 2.0;
+
+                Vector::new(1.0, 1.0, 1.0) * (1.0 - t) + Vector::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Solid(color) => *color,
+        }
+    }
+}
+
+fn address(material: &dyn Material) -> usize {
+    material as *const dyn Material as *const () as usize
 }
 
 impl Surface for Scene {
-    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>> {
         self.surfaces.hit(ray, t_range)
     }
 }
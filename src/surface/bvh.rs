@@ -0,0 +1,178 @@
+use crate::{Ray, Vector};
+
+#[derive(Copy, Clone)]
+pub struct Aabb {
+    min: Vector,
+    max: Vector,
+}
+
+impl Aabb {
+    pub const fn new(min: Vector, max: Vector) -> Self {
+        Self { min, max }
+    }
+
+    pub fn empty() -> Self {
+        Self::new(
+            Vector::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            Vector::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        )
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Vector::new(
+                self.min[0].min(other.min[0]),
+                self.min[1].min(other.min[1]),
+                self.min[2].min(other.min[2]),
+            ),
+            Vector::new(
+                self.max[0].max(other.max[0]),
+                self.max[1].max(other.max[1]),
+                self.max[2].max(other.max[2]),
+            ),
+        )
+    }
+
+    pub fn grow(&self, point: &Vector) -> Aabb {
+        self.union(&Aabb::new(*point, *point))
+    }
+
+    pub fn centroid(&self) -> Vector {
+        (self.min + self.max) / 2.0
+    }
+
+    fn longest_axis(&self) -> usize {
+        let extent = self.max - self.min;
+        if extent[0] > extent[1] && extent[0] > extent[2] {
+            0
+        } else if extent[1] > extent[2] {
+            1
+        } else {
+            2
+        }
+    }
+
+    pub fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> bool {
+        super::count_test();
+        let (mut t_min, mut t_max) = t_range;
+        for axis in 0..3 {
+            let inverse = 1.0 / ray.direction()[axis];
+            let mut t0 = (self.min[axis] - ray.origin()[axis]) * inverse;
+            let mut t1 = (self.max[axis] - ray.origin()[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            t_min = t0.max(t_min);
+            t_max = t1.min(t_max);
+            if t_max < t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+enum Node {
+    Leaf {
+        bounds: Aabb,
+        start: usize,
+        end: usize,
+    },
+    // The left child always immediately follows its parent.
+    Interior {
+        bounds: Aabb,
+        right: usize,
+    },
+}
+
+const MAX_LEAF_SIZE: usize = 4;
+
+// Bounding volume hierarchy over primitives owned by someone else, referred to by index.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    order: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(bounds: &[Aabb]) -> Self {
+        let mut ret = Self {
+            nodes: Vec::with_capacity(2 * bounds.len() / MAX_LEAF_SIZE + 1),
+            order: (0..bounds.len()).collect(),
+        };
+        if !bounds.is_empty() {
+            ret.build(bounds, 0, bounds.len());
+        }
+        ret
+    }
+
+    fn build(&mut self, bounds: &[Aabb], start: usize, end: usize) {
+        let total = self.order[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.union(&bounds[i]));
+
+        if end - start <= MAX_LEAF_SIZE {
+            self.nodes.push(Node::Leaf {
+                bounds: total,
+                start,
+                end,
+            });
+            return;
+        }
+
+        let centroids = self.order[start..end]
+            .iter()
+            .fold(Aabb::empty(), |acc, &i| acc.grow(&bounds[i].centroid()));
+        let axis = centroids.longest_axis();
+        let middle = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(middle - start, |&a, &b| {
+            bounds[a].centroid()[axis]
+                .partial_cmp(&bounds[b].centroid()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let index = self.nodes.len();
+        self.nodes.push(Node::Interior {
+            bounds: total,
+            right: 0,
+        });
+        self.build(bounds, start, middle);
+        let right_index = self.nodes.len();
+        if let Node::Interior { right, .. } = &mut self.nodes[index] {
+            *right = right_index;
+        }
+        self.build(bounds, middle, end);
+    }
+
+    // Calls `hit` with each primitive whose bounds the ray passes through, along with the nearest
+    // distance found so far. `hit` returns the distance to its primitive, if it was hit.
+    pub fn traverse<F>(&self, ray: &Ray, t_range: (f32, f32), mut hit: F)
+    where
+        F: FnMut(usize, (f32, f32)) -> Option<f32>,
+    {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut t_max = t_range.1;
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            match self.nodes[index] {
+                Node::Leaf { bounds, start, end } => {
+                    if bounds.hit(ray, (t_range.0, t_max)) {
+                        for &primitive in &self.order[start..end] {
+                            if let Some(t) = hit(primitive, (t_range.0, t_max)) {
+                                t_max = t;
+                            }
+                        }
+                    }
+                }
+                Node::Interior { bounds, right } => {
+                    if bounds.hit(ray, (t_range.0, t_max)) {
+                        stack.push(right);
+                        stack.push(index + 1);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -10,12 +10,13 @@ impl<'m> SurfaceList<'m> {
 }
 
 impl Surface for SurfaceList<'_> {
-    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>> {
         let mut min_dist = t_range.1;
         self.0
             .iter()
-            .filter_map(|s| {
-                let hit = s.hit(ray, (t_range.0, min_dist))?;
+            .enumerate()
+            .filter_map(|(i, s)| {
+                let hit = s.hit(ray, (t_range.0, min_dist))?.with_surface_index(i);
                 min_dist = hit.t;
                 Some(hit)
             })
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use super::{Material, Scatter};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+// Specular only, like `ThinFilm`: connecting to the base alone would leave the coat's reflection
+// out of photon mapping and weight it as diffuse in bidirectional path tracing.
+pub struct Coated {
+    base: Arc<dyn Material>,
+    refractive_index: f32,
+    // Transmittance of a single pass through the coat at normal incidence.
+    tint: Color,
+}
+
+impl Coated {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(base: Arc<dyn Material>, refractive_index: f32, tint: Color) -> Arc<dyn Material> {
+        Arc::new(Self {
+            base,
+            refractive_index,
+            tint,
+        })
+    }
+
+    fn reflectance(&self, cosine: f32) -> f32 {
+        // Shlick approximation.
+        let r0 = ((1.0 - self.refractive_index) / (1.0 + self.refractive_index)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine.clamp(0.0, 1.0)).powi(5)
+    }
+
+    // Fraction of diffuse light inside the coat that is reflected back down onto the base.
+    fn internal_reflectance(&self) -> f32 {
+        let r0 = ((1.0 - self.refractive_index) / (1.0 + self.refractive_index)).powi(2);
+        let external = r0 + (1.0 - r0) / 21.0;
+        1.0 - (1.0 - external) / (self.refractive_index * self.refractive_index)
+    }
+
+    // Cosine of the refracted angle inside the coat.
+    fn refracted_cosine(&self, cosine: f32) -> f32 {
+        (1.0 - (1.0 - cosine * cosine) / (self.refractive_index * self.refractive_index)).sqrt()
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        if !hit.front_face {
+            return self.base.scatter(ray, hit, sampler);
+        }
+
+        let cosine = hit.normal.dot(&-ray.direction().unit()).min(1.0);
+        if sampler.next_1d() < self.reflectance(cosine) {
+            return Some(Scatter {
+                ray: Ray::new(hit.point, ray.direction().unit().reflect(&hit.normal)),
+                attenuation: Color::new(1.0, 1.0, 1.0),
+            });
+        }
+
+        let Scatter {
+            ray: scattered,
+            attenuation,
+        } = self.base.scatter(ray, hit, sampler)?;
+        let exit_cosine = hit.normal.dot(&scattered.direction().unit());
+        if exit_cosine <= 0.0 {
+            // Transmission through the base, the coat only sits on the outside.
+            return Some(Scatter {
+                ray: scattered,
+                attenuation,
+            });
+        }
+
+        let path_length =
+            1.0 / self.refracted_cosine(cosine) + 1.0 / self.refracted_cosine(exit_cosine);
+        let absorption = Vector::new(
+            self.tint[0].powf(path_length),
+            self.tint[1].powf(path_length),
+            self.tint[2].powf(path_length),
+        );
+
+        // Light reflected off the inside of the coat returns to the base, and so on.
+        let base_albedo = (attenuation[0] + attenuation[1] + attenuation[2]) / 3.0;
+        let interreflection = 1.0 / (1.0 - self.internal_reflectance() * base_albedo.min(1.0));
+
+        Some(Scatter {
+            ray: scattered,
+            attenuation: (*attenuation
+                * absorption
+                * ((1.0 - self.reflectance(exit_cosine)) * interreflection))
+                .into(),
+        })
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.base.albedo(hit)
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        self.base.masked(hit)
+    }
+}
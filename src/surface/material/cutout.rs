@@ -0,0 +1,68 @@
+use std::sync::Arc;
+
+use super::{Material, Scatter};
+use crate::sampler::{self, Sampler};
+use crate::texture::Texture;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+#[derive(Copy, Clone)]
+pub enum AlphaMode {
+    // Opaque where the opacity is at least the threshold, absent elsewhere.
+    Threshold(f32),
+    // Present with probability equal to the opacity.
+    Stochastic,
+}
+
+pub struct Cutout {
+    base: Arc<dyn Material>,
+    opacity: Arc<dyn Texture>,
+    mode: AlphaMode,
+}
+
+impl Cutout {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        base: Arc<dyn Material>,
+        opacity: Arc<dyn Texture>,
+        mode: AlphaMode,
+    ) -> Arc<dyn Material> {
+        Arc::new(Self {
+            base,
+            opacity,
+            mode,
+        })
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        self.base.scatter(ray, hit, sampler)
+    }
+
+    fn evaluate(&self, hit: &Hit, outgoing: &Vector, incoming: &Vector) -> Option<(Vector, f32)> {
+        self.base.evaluate(hit, outgoing, incoming)
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.base.albedo(hit)
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        let opacity = self.opacity.value(hit.uv, &hit.point);
+        let opacity = (opacity[0] + opacity[1] + opacity[2]) / 3.0;
+
+        match self.mode {
+            AlphaMode::Threshold(threshold) => opacity < threshold,
+            AlphaMode::Stochastic => {
+                // Hashed rather than random, so that renders stay repeatable. Hit points vary
+                // enough between samples to average out.
+                let key = sampler::hash(&[
+                    hit.point[0].to_bits() as u64,
+                    hit.point[1].to_bits() as u64,
+                    hit.point[2].to_bits() as u64,
+                ]);
+                (key >> 40) as f32 / (1 << 24) as f32 >= opacity
+            }
+        }
+    }
+}
@@ -1,18 +1,20 @@
 use std::sync::Arc;
 
 use super::{Material, Scatter};
-use crate::{random_unit, surface::Hit, Color, Ray};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray};
 
 pub struct Dielectric {
     refractive_index: f32,
 }
 
 impl Dielectric {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(refractive_index: f32) -> Arc<dyn Material> {
         Arc::new(Self { refractive_index })
     }
 
-    fn reflectance(cosine: f32, index_ratio: f32) -> f32 {
+    pub(super) fn reflectance(cosine: f32, index_ratio: f32) -> f32 {
         // Shlick approximation.
         let r0 = ((1.0 - index_ratio) / (1.0 + index_ratio)).powi(2);
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
@@ -20,7 +22,7 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
         let cosine = hit.normal.dot(&-ray.direction().unit()).min(1.0);
         let sine = (1.0 - cosine * cosine).sqrt();
         let index_ratio = if hit.front_face {
@@ -33,7 +35,7 @@ impl Material for Dielectric {
             ray: Ray::new(
                 hit.point,
                 if index_ratio * sine > 1.0
-                    || Self::reflectance(cosine, index_ratio) > random_unit()
+                    || Self::reflectance(cosine, index_ratio) > sampler.next_1d()
                 {
                     // Total internal reflection
                     ray.direction().unit().reflect(&hit.normal)
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+
+use super::{Material, Scatter};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray};
+
+pub struct DiffuseLight {
+    radiance: Color,
+}
+
+impl DiffuseLight {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(radiance: Color) -> Arc<dyn Material> {
+        Arc::new(Self { radiance })
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _hit: &Hit, _sampler: &mut dyn Sampler) -> Option<Scatter> {
+        None
+    }
+
+    fn emitted(&self, hit: &Hit) -> Color {
+        if hit.front_face {
+            self.radiance
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+}
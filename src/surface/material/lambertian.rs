@@ -1,6 +1,8 @@
+use std::f32::consts::PI;
 use std::sync::Arc;
 
 use super::{Material, Scatter};
+use crate::sampler::Sampler;
 use crate::{surface::Hit, Color, Ray, Vector};
 
 pub struct Lambertian {
@@ -8,14 +10,15 @@ pub struct Lambertian {
 }
 
 impl Lambertian {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(albedo: Color) -> Arc<dyn Material> {
         Arc::new(Self { albedo })
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray: &Ray, hit: &Hit) -> Option<Scatter> {
-        let mut direction = hit.normal + Vector::random_unit_vector();
+    fn scatter(&self, _ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let mut direction = hit.normal + sampler.unit_vector();
         if direction.near_zero() {
             direction = hit.normal;
         }
@@ -24,4 +27,17 @@ impl Material for Lambertian {
             attenuation: self.albedo,
         })
     }
+
+    fn albedo(&self, _hit: &Hit) -> Color {
+        self.albedo
+    }
+
+    fn evaluate(&self, hit: &Hit, outgoing: &Vector, incoming: &Vector) -> Option<(Vector, f32)> {
+        let cosine = hit.normal.dot(&incoming.unit());
+        Some(if cosine <= 0.0 || hit.normal.dot(outgoing) <= 0.0 {
+            (Vector::new(0.0, 0.0, 0.0), 0.0)
+        } else {
+            (*self.albedo / PI, cosine / PI)
+        })
+    }
 }
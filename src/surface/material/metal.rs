@@ -1,7 +1,8 @@
 use std::sync::Arc;
 
 use super::{Material, Scatter};
-use crate::{surface::Hit, Color, Ray, Vector};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray};
 
 pub struct Metal {
     albedo: Color,
@@ -9,6 +10,7 @@ pub struct Metal {
 }
 
 impl Metal {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(albedo: Color, fuzz: f32) -> Arc<dyn Material> {
         Arc::new(Self {
             albedo,
@@ -18,16 +20,17 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+    fn albedo(&self, _hit: &Hit) -> Color {
+        self.albedo
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
         let reflected = ray.direction().unit().reflect(&hit.normal);
         if reflected.dot(&hit.normal) <= 0.0 {
             None
         } else {
             Some(Scatter {
-                ray: Ray::new(
-                    hit.point,
-                    reflected + self.fuzz * Vector::random_in_unit_sphere(),
-                ),
+                ray: Ray::new(hit.point, reflected + self.fuzz * sampler.in_unit_sphere()),
                 attenuation: self.albedo,
             })
         }
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use super::{Material, Scatter};
+use crate::sampler::{self, Sampler};
+use crate::texture::Texture;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+pub struct Mix {
+    first: Arc<dyn Material>,
+    second: Arc<dyn Material>,
+    mask: Arc<dyn Texture>,
+}
+
+impl Mix {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        factor: f32,
+    ) -> Arc<dyn Material> {
+        Self::with_mask(first, second, Arc::new(Color::new(factor, factor, factor)))
+    }
+
+    pub fn with_mask(
+        first: Arc<dyn Material>,
+        second: Arc<dyn Material>,
+        mask: Arc<dyn Texture>,
+    ) -> Arc<dyn Material> {
+        Arc::new(Self {
+            first,
+            second,
+            mask,
+        })
+    }
+
+    fn factor(&self, hit: &Hit) -> f32 {
+        let mask = self.mask.value(hit.uv, &hit.point);
+        ((mask[0] + mask[1] + mask[2]) / 3.0).clamp(0.0, 1.0)
+    }
+
+    // The chance of taking the second material, given that the surface is present. Where one
+    // child is cut out, the hit can only have been kept by the other.
+    fn weight(&self, hit: &Hit) -> f32 {
+        match (self.first.masked(hit), self.second.masked(hit)) {
+            (true, false) => 1.0,
+            (false, true) => 0.0,
+            _ => self.factor(hit),
+        }
+    }
+}
+
+impl Material for Mix {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        if sampler.next_1d() < self.weight(hit) {
+            self.second.scatter(ray, hit, sampler)
+        } else {
+            self.first.scatter(ray, hit, sampler)
+        }
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        let factor = self.factor(hit);
+        (*self.first.albedo(hit) * (1.0 - factor) + *self.second.albedo(hit) * factor).into()
+    }
+
+    fn evaluate(&self, hit: &Hit, outgoing: &Vector, incoming: &Vector) -> Option<(Vector, f32)> {
+        let factor = self.weight(hit);
+        let (first, first_pdf) = self.first.evaluate(hit, outgoing, incoming)?;
+        let (second, second_pdf) = self.second.evaluate(hit, outgoing, incoming)?;
+        Some((
+            first * (1.0 - factor) + second * factor,
+            first_pdf * (1.0 - factor) + second_pdf * factor,
+        ))
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        match (self.first.masked(hit), self.second.masked(hit)) {
+            (false, false) => false,
+            (true, true) => true,
+            (first, _) => {
+                // Picks a child by the factor, as scatter does. Hashed like stochastic cutouts, but
+                // salted so as not to follow a cutout child's own choice.
+                let key = sampler::hash(&[
+                    hit.point[0].to_bits() as u64,
+                    hit.point[1].to_bits() as u64,
+                    hit.point[2].to_bits() as u64,
+                    1,
+                ]);
+                let second = ((key >> 40) as f32 / (1 << 24) as f32) < self.factor(hit);
+                first != second
+            }
+        }
+    }
+}
@@ -1,13 +1,28 @@
+mod coated;
+mod cutout;
 mod dielectric;
+mod diffuse_light;
 mod lambertian;
 mod metal;
+mod mix;
+mod normal_map;
+mod subsurface;
+mod thin_film;
 
+pub use coated::Coated;
+pub use cutout::{AlphaMode, Cutout};
 pub use dielectric::Dielectric;
+pub use diffuse_light::DiffuseLight;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
+pub use mix::Mix;
+pub use normal_map::{Bump, NormalMap};
+pub use subsurface::{Subsurface, SubsurfaceMode};
+pub use thin_film::{FilmMode, ThinFilm};
 
 use super::Hit;
-use crate::{Color, Ray};
+use crate::sampler::Sampler;
+use crate::{Color, Ray, Vector};
 
 pub struct Scatter {
     pub ray: Ray,
@@ -15,5 +30,33 @@ pub struct Scatter {
 }
 
 pub trait Material: Sync + Send {
-    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter>;
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter>;
+
+    fn emitted(&self, _hit: &Hit) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // The BSDF (without the cosine term) for light arriving from `incoming` and leaving towards
+    // `outgoing`, along with the solid angle pdf with which `scatter` would sample `incoming` from
+    // `outgoing`. Both directions point away from the surface. Materials which can't be evaluated
+    // this way, such as specular ones, return `None` and can't be connected to by bidirectional
+    // integrators.
+    fn evaluate(
+        &self,
+        _hit: &Hit,
+        _outgoing: &Vector,
+        _incoming: &Vector,
+    ) -> Option<(Vector, f32)> {
+        None
+    }
+
+    // Fraction of light reflected overall, as a guide for denoising and compositing.
+    fn albedo(&self, _hit: &Hit) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    // Whether the surface should be treated as absent at this hit, for cutouts.
+    fn masked(&self, _hit: &Hit) -> bool {
+        false
+    }
 }
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use super::{Material, Scatter};
+use crate::sampler::Sampler;
+use crate::texture::Texture;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+pub struct NormalMap {
+    base: Arc<dyn Material>,
+    // Tangent space normals, encoded in [0, 1].
+    normals: Arc<dyn Texture>,
+}
+
+impl NormalMap {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(base: Arc<dyn Material>, normals: Arc<dyn Texture>) -> Arc<dyn Material> {
+        Arc::new(Self { base, normals })
+    }
+
+    // The hit with its shading normal taken from the map, if the map gives one.
+    fn shade<'m>(&self, hit: &Hit<'m>) -> Option<Hit<'m>> {
+        let encoded = self.normals.value(hit.uv, &hit.point);
+        let (tangent, bitangent, normal) = hit.shading_frame();
+        let perturbed = tangent * (2.0 * encoded[0] - 1.0)
+            + bitangent * (2.0 * encoded[1] - 1.0)
+            + normal * (2.0 * encoded[2] - 1.0);
+        if perturbed.near_zero() {
+            None
+        } else {
+            Some(hit.with_shading_normal(perturbed.unit()))
+        }
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let hit = match self.shade(hit) {
+            Some(hit) => hit,
+            None => return self.base.scatter(ray, hit, sampler),
+        };
+        self.base
+            .scatter(ray, &hit, sampler)
+            .filter(|scatter| !hit.leaks(scatter.ray.direction()))
+    }
+
+    fn evaluate(&self, hit: &Hit, outgoing: &Vector, incoming: &Vector) -> Option<(Vector, f32)> {
+        let hit = match self.shade(hit) {
+            Some(hit) => hit,
+            None => return self.base.evaluate(hit, outgoing, incoming),
+        };
+        evaluate_shaded(self.base.as_ref(), &hit, outgoing, incoming)
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.base.albedo(hit)
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        self.base.masked(hit)
+    }
+}
+
+pub struct Bump {
+    base: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    strength: f32,
+}
+
+impl Bump {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        base: Arc<dyn Material>,
+        height: Arc<dyn Texture>,
+        strength: f32,
+    ) -> Arc<dyn Material> {
+        Arc::new(Self {
+            base,
+            height,
+            strength,
+        })
+    }
+
+    fn height(&self, hit: &Hit, point: &Vector) -> f32 {
+        let value = self.height.value(hit.uv_at(point), point);
+        (value[0] + value[1] + value[2]) / 3.0
+    }
+
+    // The hit with its shading normal tilted by the slope of the height.
+    fn shade<'m>(&self, hit: &Hit<'m>) -> Hit<'m> {
+        // The height is differentiated along the surface, moving the texture coordinates with
+        // the point so that both solid and image textures vary.
+        let (tangent, bitangent, normal) = hit.shading_frame();
+        let height = self.height(hit, &hit.point);
+        let du = (self.height(hit, &(hit.point + tangent * BUMP_EPSILON)) - height) / BUMP_EPSILON;
+        let dv =
+            (self.height(hit, &(hit.point + bitangent * BUMP_EPSILON)) - height) / BUMP_EPSILON;
+
+        let perturbed = normal - (tangent * du + bitangent * dv) * self.strength;
+        hit.with_shading_normal(perturbed.unit())
+    }
+}
+
+// Finite difference step, in scene units.
+const BUMP_EPSILON: f32 = 1e-3;
+
+impl Material for Bump {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let hit = self.shade(hit);
+        self.base
+            .scatter(ray, &hit, sampler)
+            .filter(|scatter| !hit.leaks(scatter.ray.direction()))
+    }
+
+    fn evaluate(&self, hit: &Hit, outgoing: &Vector, incoming: &Vector) -> Option<(Vector, f32)> {
+        evaluate_shaded(self.base.as_ref(), &self.shade(hit), outgoing, incoming)
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.base.albedo(hit)
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        self.base.masked(hit)
+    }
+}
+
+// Evaluates `base` with a perturbed shading normal, leaving out light which `scatter` would have
+// filtered for leaking through the surface.
+fn evaluate_shaded(
+    base: &dyn Material,
+    hit: &Hit,
+    outgoing: &Vector,
+    incoming: &Vector,
+) -> Option<(Vector, f32)> {
+    base.evaluate(hit, outgoing, incoming).map(|evaluated| {
+        if hit.leaks(incoming) {
+            (Vector::new(0.0, 0.0, 0.0), 0.0)
+        } else {
+            evaluated
+        }
+    })
+}
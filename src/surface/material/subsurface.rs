@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use super::{Dielectric, Material, Scatter};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+// Scattering events after which light still inside the medium is taken to be absorbed.
+const MAX_STEPS: usize = 1024;
+
+#[derive(Copy, Clone)]
+pub enum SubsurfaceMode {
+    // Trace a volumetric random walk inside the surface.
+    RandomWalk,
+    // Collapse multiple scattering into a diffuse lobe using the dipole diffusion albedo.
+    Diffusion,
+}
+
+pub struct Subsurface {
+    // Single-scattering albedo, per channel.
+    albedo: Vector,
+    // Mean free path in scene units, per channel.
+    mean_free_path: Vector,
+    refractive_index: f32,
+    mode: SubsurfaceMode,
+}
+
+impl Subsurface {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        albedo: Color,
+        mean_free_path: Vector,
+        refractive_index: f32,
+        mode: SubsurfaceMode,
+    ) -> Arc<dyn Material> {
+        Arc::new(Self {
+            albedo: *albedo,
+            mean_free_path,
+            refractive_index,
+            mode,
+        })
+    }
+
+    fn extinction(&self, channel: usize) -> f32 {
+        1.0 / self.mean_free_path[channel]
+    }
+
+    fn transmittance(&self, distance: f32) -> Vector {
+        Vector::new(
+            (-self.extinction(0) * distance).exp(),
+            (-self.extinction(1) * distance).exp(),
+            (-self.extinction(2) * distance).exp(),
+        )
+    }
+
+    // Total diffuse reflectance of a semi-infinite slab (Jensen et al. 2001).
+    fn diffusion_albedo(&self) -> Vector {
+        let eta = self.refractive_index;
+        let fresnel_diffuse = -1.440 / (eta * eta) + 0.710 / eta + 0.668 + 0.0636 * eta;
+        let a = (1.0 + fresnel_diffuse) / (1.0 - fresnel_diffuse);
+
+        let channel = |albedo: f32| {
+            let root = (3.0 * (1.0 - albedo)).sqrt();
+            albedo / 2.0 * (1.0 + (-4.0 / 3.0 * a * root).exp()) * (-root).exp()
+        };
+        Vector::new(
+            channel(self.albedo[0]),
+            channel(self.albedo[1]),
+            channel(self.albedo[2]),
+        )
+    }
+
+    fn enter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let direction = ray.direction().unit();
+        let cosine = hit.normal.dot(&-direction).min(1.0);
+
+        if Dielectric::reflectance(cosine, 1.0 / self.refractive_index) > sampler.next_1d() {
+            return Some(Scatter {
+                ray: Ray::new(hit.point, direction.reflect(&hit.normal)),
+                attenuation: Color::new(1.0, 1.0, 1.0),
+            });
+        }
+
+        match self.mode {
+            SubsurfaceMode::RandomWalk => self.walk(
+                Ray::new(
+                    hit.point,
+                    direction.refract(&hit.normal, 1.0 / self.refractive_index),
+                ),
+                hit,
+                sampler,
+            ),
+            SubsurfaceMode::Diffusion => {
+                let mut direction = hit.normal + sampler.unit_vector();
+                if direction.near_zero() {
+                    direction = hit.normal;
+                }
+                Some(Scatter {
+                    ray: Ray::new(hit.point, direction),
+                    attenuation: self.diffusion_albedo().into(),
+                })
+            }
+        }
+    }
+
+    // Follows light from `ray`, inside the medium, until it leaves through the surface of `hit`.
+    // Only the way out is returned, so that however often light scatters inside, it counts as a
+    // single bounce towards the path's depth.
+    fn walk(&self, mut ray: Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let surface = hit.surface()?;
+        let mut throughput = Vector::new(1.0, 1.0, 1.0);
+        for _ in 0..MAX_STEPS {
+            let direction = ray.direction().unit();
+            let boundary =
+                surface.hit(&Ray::new(*ray.origin(), direction), (1e-3, f32::INFINITY))?;
+            let distance = boundary.t;
+
+            // Pick a channel to drive distance sampling, and weight by the average pdf over all
+            // three so that chromatic media stay unbiased.
+            let channel = ((sampler.next_1d() * 3.0) as usize).min(2);
+            let free_flight = -(1.0 - sampler.next_1d()).ln() / self.extinction(channel);
+
+            if free_flight < distance {
+                let transmittance = self.transmittance(free_flight);
+                let pdf = (0..3)
+                    .map(|c| self.extinction(c) * transmittance[c])
+                    .sum::<f32>()
+                    / 3.0;
+                throughput = throughput
+                    * Vector::new(
+                        self.albedo[0] * self.extinction(0) * transmittance[0] / pdf,
+                        self.albedo[1] * self.extinction(1) * transmittance[1] / pdf,
+                        self.albedo[2] * self.extinction(2) * transmittance[2] / pdf,
+                    );
+                ray = Ray::new(
+                    ray.origin() + &(direction * free_flight),
+                    sampler.unit_vector(),
+                );
+                continue;
+            }
+
+            let transmittance = self.transmittance(distance);
+            let probability = (transmittance[0] + transmittance[1] + transmittance[2]) / 3.0;
+            throughput = throughput * transmittance / probability;
+
+            let cosine = boundary.normal.dot(&-direction).min(1.0);
+            let sine = (1.0 - cosine * cosine).sqrt();
+            if self.refractive_index * sine > 1.0
+                || Dielectric::reflectance(cosine, self.refractive_index) > sampler.next_1d()
+            {
+                // Stay inside the medium.
+                ray = Ray::new(boundary.point, direction.reflect(&boundary.normal));
+                continue;
+            }
+            return Some(Scatter {
+                ray: Ray::new(
+                    boundary.point,
+                    direction.refract(&boundary.normal, self.refractive_index),
+                ),
+                attenuation: throughput.into(),
+            });
+        }
+        None
+    }
+}
+
+impl Material for Subsurface {
+    fn albedo(&self, _hit: &Hit) -> Color {
+        self.albedo.into()
+    }
+
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        if hit.front_face {
+            self.enter(ray, hit, sampler)
+        } else {
+            // Light starting inside the medium.
+            self.walk(Ray::new(*ray.origin(), *ray.direction()), hit, sampler)
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use once_cell::sync::OnceCell;
+
+use super::{Material, Scatter};
+use crate::sampler::Sampler;
+use crate::{surface::Hit, Color, Ray, Vector};
+
+#[derive(Copy, Clone)]
+pub enum FilmMode {
+    // One representative wavelength per channel.
+    Rgb,
+    // Integrate over the visible spectrum and project onto RGB.
+    Spectral,
+}
+
+// Has no `evaluate` on purpose, so bidirectional integrators treat it as specular. The film adds a
+// mirror lobe on top of the base's, which a single BSDF value and pdf can't describe, and the base
+// is only reached by paths which scatter through the film.
+pub struct ThinFilm {
+    base: Arc<dyn Material>,
+    // In nanometres.
+    thickness: f32,
+    film_index: f32,
+    substrate_index: f32,
+    mode: FilmMode,
+}
+
+const RGB_WAVELENGTHS: [f32; 3] = [650.0, 510.0, 475.0];
+const SPECTRAL_SAMPLES: usize = 32;
+
+impl ThinFilm {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(
+        base: Arc<dyn Material>,
+        thickness: f32,
+        film_index: f32,
+        substrate_index: f32,
+        mode: FilmMode,
+    ) -> Arc<dyn Material> {
+        Arc::new(Self {
+            base,
+            thickness,
+            film_index,
+            substrate_index,
+            mode,
+        })
+    }
+
+    fn fresnel(n_i: f32, cos_i: f32, n_t: f32, cos_t: f32) -> (f32, f32) {
+        (
+            (n_i * cos_i - n_t * cos_t) / (n_i * cos_i + n_t * cos_t),
+            (n_t * cos_i - n_i * cos_t) / (n_t * cos_i + n_i * cos_t),
+        )
+    }
+
+    // Airy reflectance of a single film, averaged over both polarizations.
+    fn airy(&self, wavelength: f32, cosine: f32, outer_index: f32, inner_index: f32) -> f32 {
+        let sine_squared = 1.0 - cosine * cosine;
+
+        let film_cos_squared = 1.0 - sine_squared * (outer_index / self.film_index).powi(2);
+        let inner_cos_squared = 1.0 - sine_squared * (outer_index / inner_index).powi(2);
+        if film_cos_squared <= 0.0 || inner_cos_squared <= 0.0 {
+            // Total internal reflection at one of the interfaces.
+            return 1.0;
+        }
+        let film_cos = film_cos_squared.sqrt();
+        let inner_cos = inner_cos_squared.sqrt();
+
+        let top = Self::fresnel(outer_index, cosine, self.film_index, film_cos);
+        let bottom = Self::fresnel(self.film_index, film_cos, inner_index, inner_cos);
+        let phase = 4.0 * PI * self.film_index * self.thickness * film_cos / wavelength;
+
+        let reflectance = |r1: f32, r2: f32| {
+            let interference = 2.0 * r1 * r2 * phase.cos();
+            (r1 * r1 + r2 * r2 + interference) / (1.0 + r1 * r1 * r2 * r2 + interference)
+        };
+
+        ((reflectance(top.0, bottom.0) + reflectance(top.1, bottom.1)) / 2.0).clamp(0.0, 1.0)
+    }
+
+    fn reflectance(&self, cosine: f32, front_face: bool) -> Vector {
+        let (outer, inner) = if front_face {
+            (1.0, self.substrate_index)
+        } else {
+            (self.substrate_index, 1.0)
+        };
+
+        match self.mode {
+            FilmMode::Rgb => Vector::new(
+                self.airy(RGB_WAVELENGTHS[0], cosine, outer, inner),
+                self.airy(RGB_WAVELENGTHS[1], cosine, outer, inner),
+                self.airy(RGB_WAVELENGTHS[2], cosine, outer, inner),
+            ),
+            FilmMode::Spectral => {
+                let mut ret = Vector::new(0.0, 0.0, 0.0);
+                for (wavelength, weight) in spectral_weights() {
+                    ret += &(*weight * self.airy(*wavelength, cosine, outer, inner));
+                }
+                Vector::new(
+                    ret[0].clamp(0.0, 1.0),
+                    ret[1].clamp(0.0, 1.0),
+                    ret[2].clamp(0.0, 1.0),
+                )
+            }
+        }
+    }
+}
+
+// Multi-lobe fit to the CIE 1931 colour matching functions (Wyman, Sloan and Shirley 2013).
+fn cie_xyz(wavelength: f32) -> Vector {
+    let lobe = |mean: f32, low: f32, high: f32| {
+        let t = (wavelength - mean) * if wavelength < mean { low } else { high };
+        (-0.5 * t * t).exp()
+    };
+
+    Vector::new(
+        1.056 * lobe(599.8, 0.0264, 0.0323) + 0.362 * lobe(442.0, 0.0624, 0.0374)
+            - 0.065 * lobe(501.1, 0.0490, 0.0382),
+        0.821 * lobe(568.8, 0.0213, 0.0247) + 0.286 * lobe(530.9, 0.0613, 0.0322),
+        1.217 * lobe(437.0, 0.0845, 0.0278) + 0.681 * lobe(459.0, 0.0385, 0.0725),
+    )
+}
+
+fn xyz_to_rgb(xyz: Vector) -> Vector {
+    Vector::new(
+        3.2406 * xyz[0] - 1.5372 * xyz[1] - 0.4986 * xyz[2],
+        -0.9689 * xyz[0] + 1.8758 * xyz[1] + 0.0415 * xyz[2],
+        0.0557 * xyz[0] - 0.2040 * xyz[1] + 1.0570 * xyz[2],
+    )
+}
+
+// Per-wavelength RGB weights, normalized so that a flat spectrum maps to white.
+fn spectral_weights() -> &'static [(f32, Vector)] {
+    static WEIGHTS: OnceCell<Vec<(f32, Vector)>> = OnceCell::new();
+    WEIGHTS.get_or_init(|| {
+        let step = (780.0 - 380.0) / SPECTRAL_SAMPLES as f32;
+        let samples: Vec<_> = (0..SPECTRAL_SAMPLES)
+            .map(|i| {
+                let wavelength = 380.0 + (i as f32 + 0.5) * step;
+                (wavelength, xyz_to_rgb(cie_xyz(wavelength)))
+            })
+            .collect();
+
+        let mut total = Vector::new(0.0, 0.0, 0.0);
+        samples.iter().for_each(|(_, rgb)| total += rgb);
+
+        samples
+            .into_iter()
+            .map(|(wavelength, rgb)| {
+                (
+                    wavelength,
+                    Vector::new(rgb[0] / total[0], rgb[1] / total[1], rgb[2] / total[2]),
+                )
+            })
+            .collect()
+    })
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, ray: &Ray, hit: &Hit, sampler: &mut dyn Sampler) -> Option<Scatter> {
+        let cosine = hit.normal.dot(&-ray.direction().unit()).min(1.0);
+        let reflectance = self.reflectance(cosine, hit.front_face);
+        let reflect_probability = (reflectance[0] + reflectance[1] + reflectance[2]) / 3.0;
+
+        if sampler.next_1d() < reflect_probability {
+            Some(Scatter {
+                ray: Ray::new(hit.point, ray.direction().unit().reflect(&hit.normal)),
+                attenuation: (reflectance / reflect_probability).into(),
+            })
+        } else {
+            let transmittance = Vector::new(1.0, 1.0, 1.0) - reflectance;
+            self.base.scatter(ray, hit, sampler).map(|scatter| Scatter {
+                ray: scatter.ray,
+                attenuation: (*scatter.attenuation * transmittance / (1.0 - reflect_probability))
+                    .into(),
+            })
+        }
+    }
+
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.base.albedo(hit)
+    }
+
+    fn masked(&self, hit: &Hit) -> bool {
+        self.base.masked(hit)
+    }
+}
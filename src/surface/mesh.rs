@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use super::bvh::{Aabb, Bvh};
+use super::{count_test, Hit, Material, Surface};
+use crate::texture::Texture;
+use crate::{Ray, Vector};
+
+struct Vertex {
+    position: Vector,
+    normal: Vector,
+    uv: (f32, f32),
+}
+
+pub struct Mesh {
+    vertices: Vec<Vertex>,
+    triangles: Vec<[usize; 3]>,
+    material: Arc<dyn Material>,
+    bvh: Bvh,
+}
+
+impl Mesh {
+    pub fn uv_sphere(
+        center: Vector,
+        radius: f32,
+        segments: usize,
+        material: &Arc<dyn Material>,
+    ) -> Self {
+        let rings = segments / 2;
+        let mut vertices = Vec::with_capacity((rings + 1) * (segments + 1));
+        for ring in 0..=rings {
+            let theta = ring as f32 / rings as f32 * PI;
+            for segment in 0..=segments {
+                // Share the position along the seam, so that displacement can't tear it open.
+                let phi = (segment % segments) as f32 / segments as f32 * 2.0 * PI;
+                let normal = Vector::new(
+                    -phi.cos() * theta.sin(),
+                    -theta.cos(),
+                    phi.sin() * theta.sin(),
+                );
+                vertices.push(Vertex {
+                    position: center + normal * radius,
+                    normal,
+                    uv: (segment as f32 / segments as f32, ring as f32 / rings as f32),
+                });
+            }
+        }
+
+        let index = |ring: usize, segment: usize| ring * (segments + 1) + segment;
+        let mut triangles = Vec::with_capacity(2 * rings * segments);
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let (a, b) = (index(ring, segment), index(ring, segment + 1));
+                let (c, d) = (index(ring + 1, segment), index(ring + 1, segment + 1));
+                if ring != 0 {
+                    triangles.push([a, b, d]);
+                }
+                if ring != rings - 1 {
+                    triangles.push([a, d, c]);
+                }
+            }
+        }
+
+        Self {
+            vertices,
+            triangles,
+            material: Arc::clone(material),
+            bvh: Bvh::new(&[]),
+        }
+    }
+
+    // Split every triangle into four, `levels` times over.
+    pub fn tessellate(mut self, levels: usize) -> Self {
+        for _ in 0..levels {
+            let mut midpoints = HashMap::new();
+            let mut triangles = Vec::with_capacity(self.triangles.len() * 4);
+            for [a, b, c] in std::mem::take(&mut self.triangles) {
+                let ab = self.midpoint(&mut midpoints, a, b);
+                let bc = self.midpoint(&mut midpoints, b, c);
+                let ca = self.midpoint(&mut midpoints, c, a);
+                triangles.extend_from_slice(&[[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+            }
+            self.triangles = triangles;
+        }
+        self
+    }
+
+    fn midpoint(
+        &mut self,
+        cache: &mut HashMap<(usize, usize), usize>,
+        a: usize,
+        b: usize,
+    ) -> usize {
+        let vertices = &mut self.vertices;
+        *cache.entry((a.min(b), a.max(b))).or_insert_with(|| {
+            let (a, b) = (&vertices[a], &vertices[b]);
+            let vertex = Vertex {
+                position: (a.position + b.position) / 2.0,
+                normal: (a.normal + b.normal).unit(),
+                uv: ((a.uv.0 + b.uv.0) / 2.0, (a.uv.1 + b.uv.1) / 2.0),
+            };
+            vertices.push(vertex);
+            vertices.len() - 1
+        })
+    }
+
+    // Move each vertex along its normal by the texture value, then recompute smooth normals.
+    pub fn displace(mut self, texture: &dyn Texture, scale: f32) -> Self {
+        for vertex in &mut self.vertices {
+            let height = texture.value(vertex.uv, &vertex.position);
+            let height = (height[0] + height[1] + height[2]) / 3.0;
+            vertex.position = vertex.position + vertex.normal * (height * scale);
+        }
+
+        // Accumulate by position rather than index, so that seams stay smooth.
+        let key = |v: &Vector| [v[0].to_bits(), v[1].to_bits(), v[2].to_bits()];
+        let mut normals: HashMap<_, Vector> = HashMap::new();
+        for &[a, b, c] in &self.triangles {
+            let (pa, pb, pc) = (
+                self.vertices[a].position,
+                self.vertices[b].position,
+                self.vertices[c].position,
+            );
+            // Area weighted.
+            let face = (pb - pa).cross(&(pc - pa));
+            for p in &[pa, pb, pc] {
+                *normals
+                    .entry(key(p))
+                    .or_insert_with(|| Vector::new(0.0, 0.0, 0.0)) += &face;
+            }
+        }
+        for vertex in &mut self.vertices {
+            if let Some(normal) = normals.get(&key(&vertex.position)) {
+                if !normal.near_zero() {
+                    vertex.normal = normal.unit();
+                }
+            }
+        }
+        self
+    }
+
+    pub fn build(mut self) -> Box<dyn Surface> {
+        let bounds: Vec<_> = self
+            .triangles
+            .iter()
+            .map(|triangle| {
+                triangle.iter().fold(Aabb::empty(), |acc, &i| {
+                    acc.grow(&self.vertices[i].position)
+                })
+            })
+            .collect();
+        self.bvh = Bvh::new(&bounds);
+        Box::new(self)
+    }
+
+    fn triangle_hit(&self, index: usize, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>> {
+        // Möller–Trumbore.
+        count_test();
+        let [a, b, c] = self.triangles[index];
+        let (a, b, c) = (&self.vertices[a], &self.vertices[b], &self.vertices[c]);
+        let edge1 = b.position - a.position;
+        let edge2 = c.position - a.position;
+
+        let p = ray.direction().cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < 1e-12 {
+            return None;
+        }
+        let inverse = 1.0 / determinant;
+
+        let s = ray.origin() - &a.position;
+        let u = s.dot(&p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = ray.direction().dot(&q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = edge2.dot(&q) * inverse;
+        if t < t_range.0 || t > t_range.1 {
+            return None;
+        }
+
+        let w = 1.0 - u - v;
+        let shading_normal = (a.normal * w + b.normal * u + c.normal * v).unit();
+        let mut geometric_normal = edge1.cross(&edge2).unit();
+        if geometric_normal.dot(&shading_normal) < 0.0 {
+            geometric_normal = -geometric_normal;
+        }
+
+        let duv1 = (b.uv.0 - a.uv.0, b.uv.1 - a.uv.1);
+        let duv2 = (c.uv.0 - a.uv.0, c.uv.1 - a.uv.1);
+        let uv_determinant = duv1.0 * duv2.1 - duv2.0 * duv1.1;
+        let (tangent, bitangent) = if uv_determinant.abs() < 1e-12 {
+            (Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0))
+        } else {
+            (
+                (edge1 * duv2.1 - edge2 * duv1.1) / uv_determinant,
+                (edge2 * duv1.0 - edge1 * duv2.0) / uv_determinant,
+            )
+        };
+
+        Some(
+            Hit::new(
+                ray,
+                ray.at(t),
+                geometric_normal,
+                tangent,
+                (
+                    a.uv.0 * w + b.uv.0 * u + c.uv.0 * v,
+                    a.uv.1 * w + b.uv.1 * u + c.uv.1 * v,
+                ),
+                self.material.as_ref(),
+                t,
+            )
+            .with_bitangent(bitangent)
+            .with_shading_normal(shading_normal)
+            .with_primitive(self, index),
+        )
+    }
+}
+
+impl Surface for Mesh {
+    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>> {
+        let mut closest = None;
+        self.bvh.traverse(ray, t_range, |index, t_range| {
+            let hit = self.triangle_hit(index, ray, t_range)?;
+            if self.material.masked(&hit) {
+                return None;
+            }
+            let t = hit.t;
+            closest = Some(hit);
+            Some(t)
+        });
+        closest
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(self.material.as_ref())
+    }
+}
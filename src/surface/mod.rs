@@ -1,17 +1,33 @@
+mod bvh;
 mod list;
 pub mod material;
+mod mesh;
 mod sphere;
 
 pub use list::SurfaceList;
 pub use material::{Material, Scatter};
+pub use mesh::Mesh;
 pub use sphere::Sphere;
 
+use std::cell::Cell;
+
 use crate::{Ray, Vector};
 
+#[derive(Copy, Clone)]
 pub struct Hit<'m> {
     point: Vector,
+    // Shading normal, on the same side as the incoming ray.
     normal: Vector,
+    geometric_normal: Vector,
+    // Outward, along increasing u and v, as the change in point per unit of each. The bitangent is
+    // zero where the surface doesn't give it.
+    tangent: Vector,
+    bitangent: Vector,
+    uv: (f32, f32),
     material: &'m dyn Material,
+    // Which of the scene's surfaces was hit, and which of its primitives.
+    primitive: (usize, usize),
+    surface: Option<&'m dyn Surface>,
     t: f32,
     front_face: bool,
 }
@@ -21,29 +37,158 @@ impl<'m> Hit<'m> {
         ray: &Ray,
         point: Vector,
         outward_normal: Vector,
+        tangent: Vector,
+        uv: (f32, f32),
         material: &'m dyn Material,
         t: f32,
     ) -> Hit<'m> {
         assert!(outward_normal.length() - 1.0 <= 1e-3);
         let front_face = ray.direction().dot(&outward_normal) < 0.0;
+        let normal = if front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
         Hit {
             point,
-            normal: if front_face {
-                outward_normal
-            } else {
-                -outward_normal
-            },
+            normal,
+            geometric_normal: normal,
+            tangent,
+            bitangent: Vector::new(0.0, 0.0, 0.0),
+            uv,
             material,
+            primitive: (0, 0),
+            surface: None,
             t,
             front_face,
         }
     }
 
+    pub fn point(&self) -> &Vector {
+        &self.point
+    }
+
+    pub fn normal(&self) -> &Vector {
+        &self.normal
+    }
+
+    pub fn geometric_normal(&self) -> &Vector {
+        &self.geometric_normal
+    }
+
+    pub fn uv(&self) -> (f32, f32) {
+        self.uv
+    }
+
     pub fn material(&self) -> &dyn Material {
         self.material
     }
+
+    pub fn primitive(&self) -> (usize, usize) {
+        self.primitive
+    }
+
+    // The whole surface which was hit, such as a sphere or a mesh, as opposed to the scene.
+    pub fn surface(&self) -> Option<&'m dyn Surface> {
+        self.surface
+    }
+
+    pub fn with_primitive<S: Surface>(mut self, surface: &'m S, index: usize) -> Self {
+        self.primitive.1 = index;
+        self.surface = Some(surface);
+        self
+    }
+
+    // Counted in the order the scene's surfaces were added, so that it stays the same between
+    // runs.
+    pub fn with_surface_index(mut self, index: usize) -> Self {
+        self.primitive.0 = index;
+        self
+    }
+
+    pub fn with_bitangent(mut self, bitangent: Vector) -> Self {
+        self.bitangent = bitangent;
+        self
+    }
+
+    pub fn with_shading_normal(mut self, outward_normal: Vector) -> Self {
+        self.normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+        self
+    }
+
+    fn outward_normal(&self) -> Vector {
+        if self.front_face {
+            self.normal
+        } else {
+            -self.normal
+        }
+    }
+
+    // Orthonormal (tangent, bitangent, normal) frame around the outward shading normal.
+    fn shading_frame(&self) -> (Vector, Vector, Vector) {
+        let normal = self.outward_normal();
+        let mut tangent = self.tangent - normal * normal.dot(&self.tangent);
+        if tangent.near_zero() {
+            tangent = if normal[0].abs() > 0.9 {
+                Vector::new(0.0, 1.0, 0.0)
+            } else {
+                Vector::new(1.0, 0.0, 0.0)
+            }
+            .cross(&normal);
+        }
+        let tangent = tangent.unit();
+        (tangent, normal.cross(&tangent), normal)
+    }
+
+    // Texture coordinates at a point near the hit, in the surface's tangent plane.
+    fn uv_at(&self, point: &Vector) -> (f32, f32) {
+        let offset = point - &self.point;
+        let (uu, uv, vv) = (
+            self.tangent.dot(&self.tangent),
+            self.tangent.dot(&self.bitangent),
+            self.bitangent.dot(&self.bitangent),
+        );
+        let determinant = uu * vv - uv * uv;
+        if determinant <= f32::EPSILON * uu * vv {
+            return self.uv;
+        }
+        let (du, dv) = (offset.dot(&self.tangent), offset.dot(&self.bitangent));
+        (
+            self.uv.0 + (vv * du - uv * dv) / determinant,
+            self.uv.1 + (uu * dv - uv * du) / determinant,
+        )
+    }
+
+    // Whether a scattered direction is on opposite sides of the shading and geometric surfaces,
+    // which would let light through where the real surface is opaque.
+    pub fn leaks(&self, direction: &Vector) -> bool {
+        direction.dot(&self.normal) * direction.dot(&self.geometric_normal) < 0.0
+    }
+}
+
+thread_local! {
+    static TRAVERSAL_COST: Cell<usize> = const { Cell::new(0) };
+}
+
+// Counts a bounding box or primitive test made by this thread.
+fn count_test() {
+    TRAVERSAL_COST.with(|cost| cost.set(cost.get() + 1));
+}
+
+// The number of tests made by this thread since the last call.
+pub fn take_traversal_cost() -> usize {
+    TRAVERSAL_COST.with(|cost| cost.replace(0))
 }
 
 pub trait Surface: Send + Sync {
-    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit>;
+    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>>;
+
+    // What the whole surface is made of, where that is a single material.
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
 }
@@ -1,6 +1,7 @@
+use std::f32::consts::PI;
 use std::sync::Arc;
 
-use super::{Hit, Material, Surface};
+use super::{count_test, Hit, Material, Surface};
 use crate::{Ray, Vector};
 
 pub struct Sphere {
@@ -10,6 +11,7 @@ pub struct Sphere {
 }
 
 impl Sphere {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(center: Vector, radius: f32, material: &Arc<dyn Material>) -> Box<dyn Surface> {
         Box::new(Self {
             center,
@@ -17,14 +19,21 @@ impl Sphere {
             material: Arc::clone(material),
         })
     }
+
+    fn uv(outward_normal: &Vector) -> (f32, f32) {
+        let theta = (-outward_normal[1]).acos();
+        let phi = (-outward_normal[2]).atan2(outward_normal[0]) + PI;
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Surface for Sphere {
-    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit> {
+    fn hit(&self, ray: &Ray, t_range: (f32, f32)) -> Option<Hit<'_>> {
+        count_test();
         let origin_to_center = ray.origin() - &self.center;
         let a = ray.direction().length_squared();
         let half_b = ray.direction().dot(&origin_to_center);
-        let c = origin_to_center.dot(&origin_to_center) - &self.radius * &self.radius;
+        let c = origin_to_center.dot(&origin_to_center) - self.radius * self.radius;
 
         let delta = half_b * half_b - a * c;
         if delta < 0.0 {
@@ -36,17 +45,34 @@ impl Surface for Sphere {
                 .iter()
                 .map(|&r| r / a)
                 .filter(|&r| t_range.0 <= r && r <= t_range.1)
-                .nth(0)
                 .map(|r| {
                     let intersection = ray.at(r);
+                    let outward_normal = ((intersection - self.center) / self.radius).unit();
+                    let tangent = Vector::new(outward_normal[2], 0.0, -outward_normal[0]);
+                    // Along the meridian, which is undefined at the poles.
+                    let sine = tangent.length();
+                    let bitangent = if sine > 0.0 {
+                        outward_normal.cross(&tangent) * (PI * self.radius / sine)
+                    } else {
+                        Vector::new(0.0, 0.0, 0.0)
+                    };
                     Hit::new(
                         ray,
                         intersection,
-                        ((intersection - self.center) / self.radius).unit(),
+                        outward_normal,
+                        tangent * (2.0 * PI * self.radius),
+                        Self::uv(&outward_normal),
                         self.material.as_ref(),
                         r,
                     )
+                    .with_bitangent(bitangent)
+                    .with_primitive(self, 0)
                 })
+                .find(|hit| !hit.material().masked(hit))
         }
     }
+
+    fn material(&self) -> Option<&dyn Material> {
+        Some(self.material.as_ref())
+    }
 }
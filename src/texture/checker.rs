@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use super::Texture;
+use crate::{Color, Vector};
+
+pub struct Checker {
+    odd: Arc<dyn Texture>,
+    even: Arc<dyn Texture>,
+    checks: f32,
+}
+
+impl Checker {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new(odd: Arc<dyn Texture>, even: Arc<dyn Texture>, checks: f32) -> Arc<dyn Texture> {
+        Arc::new(Self { odd, even, checks })
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, uv: (f32, f32), point: &Vector) -> Color {
+        let cell = (uv.0 * self.checks).floor() + (uv.1 * self.checks).floor();
+        if cell.rem_euclid(2.0) >= 1.0 {
+            self.odd.value(uv, point)
+        } else {
+            self.even.value(uv, point)
+        }
+    }
+}
@@ -0,0 +1,17 @@
+mod checker;
+mod noise;
+
+pub use checker::Checker;
+pub use noise::Noise;
+
+use crate::{Color, Vector};
+
+pub trait Texture: Sync + Send {
+    fn value(&self, uv: (f32, f32), point: &Vector) -> Color;
+}
+
+impl Texture for Color {
+    fn value(&self, _uv: (f32, f32), _point: &Vector) -> Color {
+        *self
+    }
+}
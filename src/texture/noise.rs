@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use super::Texture;
+use crate::{Color, Vector};
+
+const POINTS: usize = 256;
+
+// Perlin noise, with turbulence.
+pub struct Noise {
+    gradients: Vec<Vector>,
+    permutations: [Vec<usize>; 3],
+    scale: f32,
+}
+
+impl Noise {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<R: Rng>(scale: f32, rng: &mut R) -> Arc<dyn Texture> {
+        let gradients = (0..POINTS)
+            .map(|_| Vector::random_unit_vector(rng))
+            .collect();
+        let mut permutation = || {
+            let mut ret: Vec<_> = (0..POINTS).collect();
+            ret.shuffle(rng);
+            ret
+        };
+
+        Arc::new(Self {
+            gradients,
+            permutations: [permutation(), permutation(), permutation()],
+            scale,
+        })
+    }
+
+    fn noise(&self, point: &Vector) -> f32 {
+        let floor = [point[0].floor(), point[1].floor(), point[2].floor()];
+        let fraction = [
+            point[0] - floor[0],
+            point[1] - floor[1],
+            point[2] - floor[2],
+        ];
+        // Hermite smoothing.
+        let smooth: Vec<_> = fraction.iter().map(|f| f * f * (3.0 - 2.0 * f)).collect();
+
+        let mut ret = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let gradient = &self.gradients[(0..3).fold(0, |acc, axis| {
+                acc ^ self.permutations[axis]
+                    [(floor[axis] as i64 + offset[axis] as i64).rem_euclid(POINTS as i64) as usize]
+            })];
+            let weight = Vector::new(
+                fraction[0] - offset[0] as f32,
+                fraction[1] - offset[1] as f32,
+                fraction[2] - offset[2] as f32,
+            );
+            ret += (0..3)
+                .map(|axis| {
+                    if offset[axis] == 1 {
+                        smooth[axis]
+                    } else {
+                        1.0 - smooth[axis]
+                    }
+                })
+                .product::<f32>()
+                * gradient.dot(&weight);
+        }
+        ret
+    }
+
+    fn turbulence(&self, point: &Vector, octaves: usize) -> f32 {
+        let mut ret = 0.0;
+        let mut point = *point;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            ret += weight * self.noise(&point);
+            weight /= 2.0;
+            point = point * 2.0;
+        }
+        ret.abs()
+    }
+}
+
+impl Texture for Noise {
+    fn value(&self, _uv: (f32, f32), point: &Vector) -> Color {
+        let value = self.turbulence(&(*point * self.scale), 7).min(1.0);
+        Color::new(value, value, value)
+    }
+}
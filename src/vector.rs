@@ -3,8 +3,7 @@ use std::str::FromStr;
 
 use once_cell::sync::OnceCell;
 use rand::distributions::{Distribution, Uniform};
-
-use crate::random_unit;
+use rand::Rng;
 
 #[derive(Copy, Clone)]
 pub struct Vector([f32; 3]);
@@ -20,42 +19,26 @@ impl Vector {
         Self([e1, e2, e3])
     }
 
-    pub fn random<D: Distribution<f32>>(dist: &D) -> Self {
-        let mut rng = rand::thread_rng();
-        Self::new(
-            dist.sample(&mut rng),
-            dist.sample(&mut rng),
-            dist.sample(&mut rng),
-        )
+    pub fn random<D: Distribution<f32>, R: Rng>(dist: &D, rng: &mut R) -> Self {
+        Self::new(dist.sample(rng), dist.sample(rng), dist.sample(rng))
     }
 
-    pub fn random_in_unit_range() -> Self {
-        Self::new(random_unit(), random_unit(), random_unit())
+    pub fn random_in_unit_range<R: Rng>(rng: &mut R) -> Self {
+        Self::new(rng.gen(), rng.gen(), rng.gen())
     }
 
-    pub fn random_in_unit_sphere() -> Self {
+    pub fn random_in_unit_sphere<R: Rng>(rng: &mut R) -> Self {
         let dist = dist();
         loop {
-            let v = Self::random(dist);
+            let v = Self::random(dist, rng);
             if v.length_squared() < 1.0 {
                 return v;
             }
         }
     }
 
-    pub fn random_unit_vector() -> Self {
-        Self::random_in_unit_sphere().unit()
-    }
-
-    pub fn random_in_unit_disk() -> Self {
-        let dist = dist();
-        let mut rng = rand::thread_rng();
-        loop {
-            let v = Self::new(dist.sample(&mut rng), dist.sample(&mut rng), 0.0);
-            if v.length_squared() < 1.0 {
-                return v;
-            }
-        }
+    pub fn random_unit_vector<R: Rng>(rng: &mut R) -> Self {
+        Self::random_in_unit_sphere(rng).unit()
     }
 
     pub fn dot(&self, rhs: &Vector) -> f32 {
@@ -82,6 +65,10 @@ impl Vector {
         self / self.length()
     }
 
+    pub fn max_component(&self) -> f32 {
+        self[0].max(self[1]).max(self[2])
+    }
+
     pub fn near_zero(&self) -> bool {
         self.0.iter().all(|e| e.abs() < 1e-8)
     }
@@ -110,12 +97,11 @@ impl FromStr for Vector {
         static MSG: &str = "Couldn't parse vector component.";
         let ns: Vec<_> = s
             .split(',')
-            .map(|ss| ss.parse::<f32>())
-            .flatten()
+            .flat_map(|ss| ss.parse::<f32>())
             .take(3)
             .collect();
         Ok(Self::new(
-            *ns.get(0).ok_or(MSG)?,
+            *ns.first().ok_or(MSG)?,
             *ns.get(1).ok_or(MSG)?,
             *ns.get(2).ok_or(MSG)?,
         ))
@@ -134,9 +120,7 @@ impl Mul<f32> for &Vector {
     type Output = Vector;
 
     fn mul(self, rhs: f32) -> Self::Output {
-        let mut ret = self.clone();
-        ret.0.iter_mut().for_each(|v| *v *= rhs);
-        ret
+        *self * rhs
     }
 }
 
@@ -151,8 +135,9 @@ impl Mul<&Vector> for f32 {
 impl Mul<f32> for Vector {
     type Output = Vector;
 
-    fn mul(self, rhs: f32) -> Self::Output {
-        rhs * &self
+    fn mul(mut self, rhs: f32) -> Self::Output {
+        self.0.iter_mut().for_each(|v| *v *= rhs);
+        self
     }
 }
 
@@ -184,7 +169,7 @@ impl Div<f32> for Vector {
     type Output = Vector;
 
     fn div(self, rhs: f32) -> Self::Output {
-        &self / rhs
+        self * (1.0 / rhs)
     }
 }
 
@@ -200,7 +185,7 @@ impl Add for Vector {
     type Output = Vector;
 
     fn add(self, rhs: Self) -> Self::Output {
-        &self + &rhs
+        Vector::new(self[0] + rhs[0], self[1] + rhs[1], self[2] + rhs[2])
     }
 }
 
@@ -214,7 +199,7 @@ impl Add<f32> for Vector {
 
 impl AddAssign<&Vector> for Vector {
     fn add_assign(&mut self, rhs: &Self) {
-        *self = &*self + &rhs;
+        *self = &*self + rhs;
     }
 }
 
@@ -246,6 +231,6 @@ impl Sub for Vector {
     type Output = Vector;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        &self - &rhs
+        self + -rhs
     }
 }